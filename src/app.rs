@@ -1,62 +1,266 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::rc::Rc;
 use std::str::FromStr;
 
 use dashmap::DashSet;
+use futures::StreamExt;
 use gloo::storage::SessionStorage;
-use gloo::storage::Storage;
-use log::{debug, warn};
-use nostr_sdk::prelude::ToBech32;
-use nostr_sdk::secp256k1::XOnlyPublicKey;
-use nostr_sdk::Url;
+use log::{debug, error};
+use nostr_sdk::prelude::{Conditions, ToBech32};
+use nostr_sdk::secp256k1::{schnorr::Signature, XOnlyPublicKey};
+use nostr_sdk::{Event, EventId, Keys, Metadata, Timestamp, Url};
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew::props;
 
+use crate::components::alert::Alert;
 use crate::components::navbar::{Navbar, Props as NavbarProps};
-use crate::services::nostr::{DelegationInfo, NostrService};
-use crate::utils::handle_keys;
+use crate::components::note::NoteProp;
+use crate::components::outbox::OutboxProp;
+use crate::components::profile::ProfileProp;
+use crate::components::published::PublishedProp;
+use crate::services::error::NostrServiceError;
+use crate::services::interactions::{Interactions, Reaction};
+use crate::services::nostr::{
+    DelegationExpiry, DelegationInfo, DirectMessage, NostrService, PublishedEvent, ScheduledNote,
+    Timeline,
+};
+use crate::services::storage::{
+    load_auto_renew, load_storage_backend, save_auto_renew, save_storage_backend, Account,
+    Accounts, RelayConfig,
+};
+use crate::services::store::{NamespacedStore, StorageBackend, Store, WebStore};
+use crate::utils::{handle_keys, parse_pubkey};
 use crate::views::{
     connect::{Connect, Props as ConnectProps},
-    home::Home,
-    settings::{DelegationInfoProp, Props as SettingsProps, Settings},
+    edit_profile::{EditProfile, ProfileFields, Props as EditProfileProps},
+    history::{History, Props as HistoryProps},
+    home::{Home, Props as HomeProps, ScheduledProp},
+    messages::{ConversationProp, Messages, Props as MessagesProps},
+    settings::{DelegationInfoProp, DelegationToken, Props as SettingsProps, Settings},
+    thread::{Props as ThreadProps, Thread},
+    unlock::{Unlock, MIN_PASSPHRASE_LEN},
 };
 
 pub enum View {
     Home,
     Connect,
     Settings,
+    Messages,
+    Profile,
+    Thread,
+    History,
 }
 
 pub enum Msg {
     /// Publish a nostr note
     SubmitNote(AttrValue),
+    /// Publish a nostr note at a unix time
+    ScheduleNote((AttrValue, u64)),
+    /// Drop a scheduled note by id
+    CancelScheduled(u64),
+    /// Publish the scheduled notes that are due
+    PublishScheduled,
+    /// Scheduled note published or failed
+    ScheduledPublished(ScheduledNote, Result<PublishedEvent, NostrServiceError>),
+    /// Publish a failed scheduled note again by id
+    RetryScheduled(u64),
     /// Completed note broadcast
-    BroadcastedEvent(AttrValue),
+    BroadcastedEvent(Result<PublishedEvent, NostrServiceError>),
+    /// Reply to the note with id
+    Reply((AttrValue, AttrValue)),
+    /// Quote the note with id
+    Quote((AttrValue, AttrValue)),
+    /// Thread view of the note with id
+    OpenThread(AttrValue),
+    /// Thread fetched
+    ThreadLoaded(Result<Vec<(usize, Event)>, NostrServiceError>),
+    /// React to the note with id
+    React((AttrValue, Reaction)),
+    /// Repost the note with id
+    Repost(AttrValue),
+    /// Published events view
+    History,
+    /// Request deletion of a published event by id
+    DeleteEvent(AttrValue),
+    /// Resend the outbox events that are due
+    ProcessOutbox,
+    /// Number of outbox resends made
+    OutboxProcessed(usize),
+    /// Resend an outbox event by id now
+    RetryOutbox(AttrValue),
+    /// Stop resending an outbox event by id
+    DiscardOutbox(AttrValue),
+    /// Reaction or repost of the note published
+    InteractionPublished(EventId, Result<PublishedEvent, NostrServiceError>),
+    /// Fetch reactions and reposts of notes
+    LoadInteractions(Vec<EventId>),
+    /// Reactions and reposts fetched
+    InteractionsLoaded(Result<Vec<Event>, NostrServiceError>),
     /// Update Connect relay
     UpdateConnectRelay(AttrValue),
     /// Add relay to client
     AddRelay(AttrValue),
     /// Remove Relay
     RemoveRelay(Url),
+    /// Relay added or removed from client
+    RelaysUpdated(Result<(), NostrServiceError>),
     /// Set remote pubkey
-    SetRemotePubkey(Option<XOnlyPublicKey>),
+    SetRemotePubkey(Result<XOnlyPublicKey, NostrServiceError>),
     /// Settings view
     Settings,
+    /// Messages view
+    Messages,
+    /// Fetch direct messages
+    LoadMessages,
+    /// Direct messages fetched
+    LoadedMessages(Result<Vec<DirectMessage>, NostrServiceError>),
+    /// Decrypt the conversation with the hex pubkey
+    DecryptConversation(AttrValue),
+    /// Direct messages decrypted
+    ConversationDecrypted(Vec<DirectMessage>),
+    /// Send direct message to pubkey
+    SendMessage((AttrValue, AttrValue)),
+    /// Direct message sent
+    MessageSent(Result<PublishedEvent, NostrServiceError>),
     // Home view
     Home,
+    /// Load the contact list and newest notes
+    LoadTimeline,
+    /// Contact list and newest notes fetched
+    TimelineLoaded(Result<Timeline, NostrServiceError>),
+    /// Fetch notes older than the timeline
+    LoadOlderNotes,
+    /// Older notes fetched
+    OlderNotesLoaded(Result<Vec<Event>, NostrServiceError>),
+    /// Check for new notes of followed pubkeys
+    PollTimeline,
+    /// New notes fetched
+    NewerNotesLoaded(Result<Vec<Event>, NostrServiceError>),
+    /// Profile editor view
+    Profile,
+    /// Fetch the current profile metadata
+    LoadProfile,
+    /// Profile metadata fetched
+    ProfileLoaded(Result<Option<Metadata>, NostrServiceError>),
+    /// Publish edited profile metadata
+    PublishProfile(ProfileFields),
+    /// Profile metadata published
+    ProfilePublished(Result<PublishedEvent, NostrServiceError>),
     /// Send delegation request
-    Delegate((u64, Vec<u64>)),
-    /// Delegation token received
-    DelegationSet,
+    Delegate(Conditions),
+    /// Delegation token received and client recreated
+    DelegationSet(Result<(), NostrServiceError>),
     /// Got delgation info
-    DelegationInfo(DelegationInfo),
+    DelegationInfo(Result<DelegationInfo, NostrServiceError>),
+    /// Remove delegation by signature
+    RemoveDelegation(AttrValue),
+    /// Import a delegation token created by another tool
+    ImportDelegation(DelegationToken),
+    /// Export session bundle encrypted with password
+    ExportBundle(AttrValue),
+    /// Import session bundle with password
+    ImportBundle((AttrValue, AttrValue)),
+    /// Bundle written to the store of the new account
+    BundleImported(Account, Result<NostrService, NostrServiceError>),
+    /// Check held delegations for expiry
+    CheckDelegationExpiry,
+    /// Delegation expiring soon or expired
+    DelegationExpiry(DelegationExpiry),
+    /// Client switched back to the remote signer after a delegation expired
+    RemoteSignerRestored(Conditions, Result<(), NostrServiceError>),
+    /// Fetch metadata of the signer and delegators
+    FetchMetadata,
+    /// Metadata fetched and cached
+    MetadataFetched(Result<(), NostrServiceError>),
+    /// Pubkeys with a verified NIP05 identifier
+    Nip05Verified(HashSet<XOnlyPublicKey>),
+    /// Use a remote signer given by pubkey or NIP05 identifier
+    AddSigner(AttrValue),
+    /// Set whether expiring delegations are renewed automatically
+    SetAutoRenew(bool),
+    /// Move the session state to another storage backend
+    SetStorageBackend(StorageBackend),
+    /// Storage backend opened
+    StoreOpened(StorageBackend, Result<Store, NostrServiceError>),
+    /// Session state copied to the opened backend
+    StoreCopied(StorageBackend, Store, Result<(), NostrServiceError>),
+    /// Decrypt the saved app key, or save a new one, with passphrase
+    Unlock(AttrValue),
+    /// New app key written to the store
+    AppKeySaved(Result<Keys, NostrServiceError>),
+    /// Drop the decrypted app key
+    Lock,
+    /// Lock and use another account
+    SwitchAccount(AttrValue),
+    /// Lock and use a new account
+    AddAccount,
     /// Log Out
     LogOut,
+    /// Dismiss error banner
+    DismissError(usize),
+    /// Request a new delegation suggested by an error banner
+    RenewDelegation(usize),
+    /// Result of async work started by the session with the generation
+    Session(u64, Box<Msg>),
+}
+
+/// Error shown as a banner above the current view
+pub struct ErrorBanner {
+    message: AttrValue,
+    /// Conditions of a delegation that would fix the error
+    renewal: Option<Conditions>,
+}
+
+impl From<String> for ErrorBanner {
+    fn from(message: String) -> Self {
+        Self {
+            message: message.into(),
+            renewal: None,
+        }
+    }
 }
 
 pub struct App {
     view: View,
     //navbar_active: bool,
-    client: NostrService,
-    broadcasted_event: Option<AttrValue>,
+    /// Session of the decrypted app key, `None` while locked
+    client: Option<NostrService>,
+    /// Generation of the session, changed whenever a session starts or ends
+    session: u64,
+    /// Session state store of all accounts, `None` while it is opened
+    store: Option<Store>,
+    storage_backend: StorageBackend,
+    accounts: Accounts,
+    /// Signatures of delegations already reported as expiring soon
+    expiry_warned: HashSet<String>,
+    /// Signer and delegator pubkeys with a verified NIP05 identifier
+    nip05_verified: HashSet<XOnlyPublicKey>,
+    broadcasted_event: Option<PublishedEvent>,
+    /// Outbox resends are in flight
+    processing_outbox: bool,
+    /// Direct messages oldest first, decrypted by conversation when opened
+    messages: Vec<DirectMessage>,
+    loading_messages: bool,
+    /// Notes of followed pubkeys, `None` until first loaded
+    timeline: Option<Timeline>,
+    loading_notes: bool,
+    /// Id of the note the thread view was opened for
+    thread_id: Option<AttrValue>,
+    /// Thread depth first with the depth of each note
+    thread: Vec<(usize, Event)>,
+    loading_thread: bool,
+    /// Reaction and repost counts of shown notes
+    interactions: Interactions,
+    /// Metadata shown in the profile editor, `None` while it is loaded
+    profile: Option<Metadata>,
+    /// Relays sent the current kind 0, otherwise publishing could replace a profile that was not read
+    profile_loaded: bool,
+    publishing_profile: bool,
+    errors: Vec<ErrorBanner>,
+    auto_renew: bool,
+    exported_bundle: Option<AttrValue>,
     name: AttrValue,
 }
 impl Component for App {
@@ -64,255 +268,1474 @@ impl Component for App {
     type Properties = ConnectProps;
 
     fn create(ctx: &Context<Self>) -> Self {
-        let connect_relay = Url::from_str("ws://localhost:8081").unwrap();
-        // let relays: HashSet = HashSet::from_iter(vec![connect_relay.clone()]);
-
-        let key: Option<String> = SessionStorage::get("priv_key").ok();
-        let keys = handle_keys(key, true).unwrap();
-        let remote_pubkey: Option<String> = SessionStorage::get("remote_pub_key").ok();
-        let remote_pubkey = match remote_pubkey {
-            Some(key) => XOnlyPublicKey::from_str(&key).ok(),
-            None => None,
+        ctx.link().send_stream(
+            NostrService::delegation_expiry_timer().map(|_| Msg::CheckDelegationExpiry),
+        );
+        ctx.link()
+            .send_stream(NostrService::timeline_timer().map(|_| Msg::PollTimeline));
+        ctx.link()
+            .send_stream(NostrService::outbox_timer().map(|_| Msg::ProcessOutbox));
+        ctx.link()
+            .send_stream(NostrService::schedule_timer().map(|_| Msg::PublishScheduled));
+
+        let storage_backend = load_storage_backend();
+        ctx.link().send_future(async move {
+            Msg::StoreOpened(storage_backend, storage_backend.open().await)
+        });
+
+        Self {
+            // navbar_active: false,
+            client: None,
+            session: 0,
+            store: None,
+            storage_backend,
+            accounts: Accounts::load(),
+            expiry_warned: HashSet::new(),
+            nip05_verified: HashSet::new(),
+            view: View::Connect,
+            broadcasted_event: None,
+            processing_outbox: false,
+            messages: vec![],
+            loading_messages: false,
+            timeline: None,
+            loading_notes: false,
+            thread_id: None,
+            thread: vec![],
+            loading_thread: false,
+            interactions: Interactions::default(),
+            profile: None,
+            profile_loaded: false,
+            publishing_profile: false,
+            errors: vec![],
+            auto_renew: load_auto_renew(),
+            exported_bundle: None,
+            name: "nostr connect".into(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            /*
+            Msg::ToggleNavbar => {
+                self.navbar_active = !self.navbar_active;
+                true
+            }
+            */
+            Msg::Unlock(passphrase) => {
+                let store = match self.account_store() {
+                    Some(store) => store,
+                    None => return false,
+                };
+                if store.has_app_key() {
+                    let started = store
+                        .load_app_key(&passphrase)
+                        .and_then(|keys| self.start_session(ctx, &keys, store));
+                    if let Err(err) = started {
+                        self.push_error(err);
+                    }
+                    return true;
+                }
+
+                // Earlier versions kept the key in plain text, it is encrypted once a passphrase is set
+                let keys = store
+                    .legacy_app_key()
+                    .and_then(|key| handle_keys(Some(key), true).ok())
+                    .unwrap_or_else(Keys::generate);
+                match store.save_app_key(&keys, &passphrase) {
+                    // The session starts once the key is known to be written
+                    Ok(()) => {
+                        let flushed = store.flush();
+                        self.send_session_future(ctx, async move {
+                            Msg::AppKeySaved(flushed.await.map(|()| keys))
+                        });
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::BundleImported(account, Ok(service)) => {
+                self.accounts.set_active(&account.id);
+                self.lock();
+                // Nothing to sign with, wait for a signer like a fresh session does
+                if service.get_remote_signer().is_none() && !service.has_delegations() {
+                    let signer = service.clone();
+                    self.send_session_future(ctx, async move {
+                        Msg::SetRemotePubkey(signer.req_signer_pub_key().await)
+                    });
+                }
+                self.client = Some(service);
+                self.save_accounts();
+                ctx.link().send_message(Msg::Home);
+                ctx.link().send_message(Msg::FetchMetadata);
+                true
+            }
+            Msg::BundleImported(account, Err(err)) => {
+                // Drop whatever was written for the new account
+                if let Some(root) = self.store.as_ref() {
+                    namespaced(root, &account).clear();
+                }
+                RelayConfig::delete(&account);
+                self.accounts.remove(&account.id);
+                self.push_error(err);
+                true
+            }
+            Msg::AppKeySaved(keys) => {
+                let store = match self.account_store() {
+                    Some(store) => store,
+                    None => return false,
+                };
+                if let Err(err) = keys.and_then(|keys| self.start_session(ctx, &keys, store)) {
+                    self.push_error(err);
+                }
+                true
+            }
+            Msg::Lock => {
+                if !self.keeps_scheduled() {
+                    self.lock();
+                }
+                true
+            }
+            Msg::SwitchAccount(id) => {
+                if id.as_str() == self.accounts.active().id || self.keeps_scheduled() {
+                    return true;
+                }
+                if self.accounts.set_active(&id) {
+                    self.lock();
+                    self.save_accounts();
+                }
+                true
+            }
+            Msg::AddAccount => {
+                if self.keeps_scheduled() {
+                    return true;
+                }
+                self.lock();
+                self.accounts.add();
+                self.save_accounts();
+                true
+            }
+            Msg::LogOut => {
+                if self.keeps_scheduled() {
+                    return true;
+                }
+                // Clear session
+                if let Some(store) = self.account_store() {
+                    store.clear();
+                }
+                self.lock();
+
+                // Forget the account unless it is the last one
+                if self.accounts.accounts.len() > 1 {
+                    let account = self.accounts.active().clone();
+                    RelayConfig::delete(&account);
+                    self.accounts.remove(&account.id);
+                    self.save_accounts();
+                }
+                true
+            }
+            Msg::SetStorageBackend(backend) if backend == self.storage_backend => false,
+            Msg::SetStorageBackend(backend) => {
+                ctx.link()
+                    .send_future(async move { Msg::StoreOpened(backend, backend.open().await) });
+                false
+            }
+            Msg::StoreOpened(backend, result) => {
+                let store = match result {
+                    Ok(store) => store,
+                    Err(err) => {
+                        self.push_error(err);
+                        if self.store.is_none() {
+                            // Fall back to the default so the app can still be unlocked
+                            self.store = Some(Rc::new(WebStore::<SessionStorage>::default()));
+                        }
+                        return true;
+                    }
+                };
+
+                // Switching backend, move the session state of every account over
+                // and only drop it from the previous one once the copy is written
+                if let Some(previous) = self.store.clone() {
+                    let copied = self.accounts.accounts.iter().try_for_each(|account| {
+                        namespaced(&store, account).copy_from(&namespaced(&previous, account))
+                    });
+                    let flushed = store.flush();
+                    ctx.link().send_future(async move {
+                        let copied = match copied {
+                            Ok(()) => flushed.await,
+                            Err(err) => Err(err),
+                        };
+                        Msg::StoreCopied(backend, store, copied)
+                    });
+                    return false;
+                }
+                self.use_store(backend, store);
+                true
+            }
+            Msg::StoreCopied(_, store, Err(err)) => {
+                // The previous backend stays in use, drop the partial copy
+                for account in &self.accounts.accounts {
+                    namespaced(&store, account).clear();
+                }
+                self.push_error(err);
+                true
+            }
+            Msg::StoreCopied(backend, store, Ok(())) => {
+                let previous = self.store.clone();
+                // Services of in-flight work write to the new store from here on
+                self.use_store(backend, store);
+                if let Some(previous) = previous {
+                    for account in &self.accounts.accounts {
+                        namespaced(&previous, account).clear();
+                    }
+                }
+                true
+            }
+            Msg::Session(session, msg) => {
+                if session != self.session {
+                    debug!("Dropping result of an ended session");
+                    return false;
+                }
+                self.update(ctx, *msg)
+            }
+            Msg::DismissError(index) => {
+                if index < self.errors.len() {
+                    self.errors.remove(index);
+                }
+                true
+            }
+            msg => {
+                // Everything else needs an unlocked session
+                let mut client = match self.client.take() {
+                    Some(client) => client,
+                    None => return false,
+                };
+                let render = self.update_session(ctx, &mut client, msg);
+                self.client = Some(client);
+                render
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let settings_cb = ctx.link().callback(|_| Msg::Settings);
+        let home_cb = ctx.link().callback(|_| Msg::Home);
+        let messages_cb = ctx.link().callback(|_| Msg::Messages);
+        let profile_cb = ctx.link().callback(|_| Msg::Profile);
+        let history_cb = ctx.link().callback(|_| Msg::History);
+        let lock_cb = ctx.link().callback(|_| Msg::Lock);
+        let switch_account_cb = ctx.link().callback(Msg::SwitchAccount);
+        let add_account_cb = ctx.link().callback(|_| Msg::AddAccount);
+
+        let props = props! {
+            NavbarProps {
+                settings_cb,
+                home_cb,
+                messages_cb,
+                profile_cb,
+                history_cb,
+                lock_cb,
+                locked: self.client.is_none(),
+                accounts: self.accounts.accounts.clone(),
+                active_account: AttrValue::from(self.accounts.active().id.clone()),
+                switch_account_cb,
+                add_account_cb
+            }
         };
 
-        let delegation_tag: Option<String> = SessionStorage::get("delegationInfo").ok();
+        html! {
+        <>
+            {
+
+            html! { <Navbar .. props />}
+            }
+
+            {
+                self.errors.iter().enumerate().map(|(index, banner)| {
+                    let dismiss_cb = ctx.link().callback(move |_| Msg::DismissError(index));
+                    let (action, action_cb) = match banner.renewal {
+                        Some(_) => (
+                            Some(AttrValue::from("Request new delegation")),
+                            ctx.link().callback(move |_| Msg::RenewDelegation(index)),
+                        ),
+                        None => (None, Callback::default()),
+                    };
+                    html! { <Alert message={banner.message.clone()} {dismiss_cb} {action} {action_cb} /> }
+                }).collect::<Html>()
+            }
+
+            {
+                match (&self.client, self.account_store()) {
+                    (Some(client), _) => self.session_view(ctx, client),
+                    (None, Some(store)) => {
+                        let unlock_cb = ctx.link().callback(Msg::Unlock);
+                        let forget_cb = ctx.link().callback(|_| Msg::LogOut);
+                        // Keyed so a confirmation left open does not carry over to the next account
+                        let account = self.accounts.active().id.clone();
+                        html! { <Unlock key={account} create={!store.has_app_key()} {unlock_cb} {forget_cb} /> }
+                    }
+                    (None, None) => html! {
+                        <p class="p-4 text-sm text-gray-500 dark:text-gray-400">{ "Opening storage" }</p>
+                    },
+                }
+            }
+        <footer class="footer">
+        </footer>
+        </>
+        }
+    }
+}
+
+impl App {
+    /// Start a session with the decrypted app keys
+    fn start_session(
+        &mut self,
+        ctx: &Context<Self>,
+        keys: &Keys,
+        store: Store,
+    ) -> Result<(), NostrServiceError> {
+        self.session += 1;
+        let account = self.accounts.active().clone();
+        let relay_config = RelayConfig::load(&account);
+        let connect_relay = relay_config.connect_relay();
+        let relays = relay_config.relays();
+
+        let remote_pubkey = store.remote_signer();
+        let has_delegations = store.has_stored_delegations();
 
         // TODO: Clean this up
         // If there is a remote pubkey saved to session sotrange then create client with that as remote pubkey
         // If there is a VALID delegation tag saved to storage create a client without a remote and use the tag
-        let (client, view) = match (remote_pubkey, delegation_tag) {
-            (Some(_remote_key), Some(_tag)) => {
-                let relays = DashSet::new();
-                // TODO: Dont hard code this
-                relays.insert(Url::from_str("ws://localhost:8081").unwrap());
-
-                let client = NostrService::new_without_remote(&keys, relays).unwrap();
+        let (client, view) = match (remote_pubkey, has_delegations) {
+            (Some(_remote_key), true) => {
+                let mut client =
+                    NostrService::new_without_remote(keys, connect_relay, relays, store, account)?;
+                client.set_remote_pubkey(remote_pubkey);
 
                 (client, View::Home)
             }
-            (Some(_remote_pubkey), None) => {
-                let client = NostrService::new(&keys, remote_pubkey, connect_relay).unwrap();
+            (Some(_remote_pubkey), false) => {
+                let client =
+                    NostrService::new(keys, remote_pubkey, connect_relay, relays, store, account)?;
                 (client, View::Home)
             }
             _ => {
-                let client = NostrService::new(&keys, None, connect_relay).unwrap();
-                let signer_pubkey_callback = ctx.link().callback(Msg::SetRemotePubkey);
+                let client = NostrService::new(keys, None, connect_relay, relays, store, account)?;
 
-                client.req_signer_pub_key(signer_pubkey_callback).unwrap();
+                let service = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::SetRemotePubkey(service.req_signer_pub_key().await)
+                });
                 (client, View::Connect)
             }
         };
 
-        Self {
-            // navbar_active: false,
-            client,
-            view,
-            broadcasted_event: None,
-            name: "nostr connect".into(),
+        if matches!(view, View::Home) {
+            ctx.link().send_message(Msg::LoadTimeline);
         }
+        self.client = Some(client);
+        self.view = view;
+        ctx.link().send_message(Msg::FetchMetadata);
+        Ok(())
     }
 
-    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+    /// Run `future` for the current session, its message is dropped if the session ends first
+    fn send_session_future<F>(&self, ctx: &Context<Self>, future: F)
+    where
+        F: Future<Output = Msg> + 'static,
+    {
+        let session = self.session;
+        ctx.link()
+            .send_future(async move { Msg::Session(session, Box::new(future.await)) });
+    }
+
+    /// Session state store of the active account
+    fn account_store(&self) -> Option<Store> {
+        let store = self.store.as_ref()?;
+        Some(Rc::new(NamespacedStore::new(
+            store.clone(),
+            self.accounts.active(),
+        )))
+    }
+
+    /// Keep the session state in `store` from now on
+    fn use_store(&mut self, backend: StorageBackend, store: Store) {
+        if let Err(err) = save_storage_backend(backend) {
+            self.push_error(err);
+        }
+        self.store = Some(store);
+        self.storage_backend = backend;
+        if let Some(store) = self.account_store() {
+            if let Some(client) = self.client.as_ref() {
+                client.set_store(store);
+            }
+        }
+    }
+
+    fn save_accounts(&mut self) {
+        if let Err(err) = self.accounts.save() {
+            self.push_error(err);
+        }
+    }
+
+    /// Refuse to end a session with scheduled notes, they are kept by the session only
+    fn keeps_scheduled(&mut self) -> bool {
+        let scheduled = self
+            .client
+            .as_ref()
+            .map_or(0, |client| client.get_scheduled().len());
+        if scheduled == 0 {
+            return false;
+        }
+        self.push_error(NostrServiceError::ScheduledPending(scheduled));
+        true
+    }
+
+    /// Drop the session and everything read with the decrypted keys
+    fn lock(&mut self) {
+        self.session += 1;
+        if let Some(client) = self.client.take() {
+            spawn_local(async move { client.shutdown().await });
+        }
+        self.broadcasted_event = None;
+        self.processing_outbox = false;
+        self.messages.clear();
+        self.timeline = None;
+        self.loading_notes = false;
+        self.thread_id = None;
+        self.thread.clear();
+        self.loading_thread = false;
+        self.interactions = Interactions::default();
+        self.profile = None;
+        self.profile_loaded = false;
+        self.publishing_profile = false;
+        self.exported_bundle = None;
+        self.expiry_warned.clear();
+        self.nip05_verified.clear();
+    }
+
+    /// Handle a message of an unlocked session
+    fn update_session(&mut self, ctx: &Context<Self>, client: &mut NostrService, msg: Msg) -> bool {
         match msg {
-            /*
-            Msg::ToggleNavbar => {
-                self.navbar_active = !self.navbar_active;
-                true
+            Msg::CheckDelegationExpiry => {
+                for notice in client.check_delegation_expiry(&mut self.expiry_warned) {
+                    ctx.link().send_message(Msg::DelegationExpiry(notice));
+                }
+                false
             }
-            */
             Msg::AddRelay(relay) => {
                 if let Ok(relay) = Url::from_str(&relay) {
-                    self.client.add_relay(relay).ok();
+                    let client = client.clone();
+                    self.send_session_future(ctx, async move {
+                        Msg::RelaysUpdated(client.add_relay(relay).await)
+                    });
                 }
                 true
             }
             Msg::RemoveRelay(relay) => {
-                self.client.remove_relay(relay);
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::RelaysUpdated(client.remove_relay(relay).await)
+                });
+                true
+            }
+            Msg::RelaysUpdated(result) => {
+                if let Err(err) = result {
+                    self.push_error(err);
+                }
                 true
             }
             Msg::UpdateConnectRelay(relay) => {
                 if let Ok(relay) = Url::from_str(&relay) {
-                    self.client.set_connect_relay(relay);
-                    let delegation_info = match self.client.get_delegation_info() {
-                        Ok(Some(info)) => Some(info),
-                        _ => None,
-                    };
-                    if delegation_info.is_none() {
-                        self.client.new_client_with_remote_signer();
-                        let signer_pubkey_callback = ctx.link().callback(Msg::SetRemotePubkey);
-                        self.client.req_signer_pub_key(signer_pubkey_callback).ok();
+                    if let Err(err) = client.set_connect_relay(relay) {
+                        self.push_error(err);
+                    }
+                    if !client.has_delegations() {
+                        let client = client.clone();
+                        self.send_session_future(ctx, async move {
+                            if let Err(err) = client.new_client_with_remote_signer().await {
+                                return Msg::SetRemotePubkey(Err(err));
+                            }
+                            Msg::SetRemotePubkey(client.req_signer_pub_key().await)
+                        });
                     }
                 }
                 true
             }
             Msg::SubmitNote(note) => {
                 debug!("Got note: {note}");
-                let event_callback = ctx.link().callback(Msg::BroadcastedEvent);
-                self.client.publish_text_note(&note, event_callback).ok();
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::BroadcastedEvent(client.publish_text_note(&note).await)
+                });
                 true
             }
-            Msg::BroadcastedEvent(event_id) => {
-                debug!("Broadcasted: {}", event_id);
-                self.broadcasted_event = Some(event_id);
+            Msg::ScheduleNote((note, publish_at)) => {
+                match client.schedule_text_note(&note, Timestamp::from(publish_at)) {
+                    Ok(scheduled) => debug!("Scheduled note {} at {}", scheduled.id, publish_at),
+                    Err(err) => self.push_error(err),
+                }
                 true
             }
-            Msg::Delegate((expiration, kinds)) => {
-                let delegate_callback = ctx.link().callback(|_| Msg::DelegationSet);
-                let delegation_info_callback = ctx.link().callback(Msg::DelegationInfo);
-
-                self.client
-                    .get_delegate(
-                        expiration,
-                        kinds,
-                        delegate_callback,
-                        delegation_info_callback,
-                    )
-                    .ok();
+            Msg::CancelScheduled(id) => {
+                client.cancel_scheduled(id);
+                true
+            }
+            Msg::PublishScheduled => {
+                let due = client.take_due_notes();
+                let render = !due.is_empty();
+                for scheduled in due {
+                    let client = client.clone();
+                    self.send_session_future(ctx, async move {
+                        let result = client.publish_text_note(&scheduled.content).await;
+                        Msg::ScheduledPublished(scheduled, result)
+                    });
+                }
+                render
+            }
+            Msg::ScheduledPublished(scheduled, Ok(published)) => {
+                debug!("Published scheduled note {}", scheduled.id);
+                self.update_session(ctx, client, Msg::BroadcastedEvent(Ok(published)))
+            }
+            Msg::ScheduledPublished(scheduled, Err(err)) => {
+                // Kept so the note is not lost, retried or cancelled from the schedule
+                client.fail_scheduled(scheduled, err.to_string());
+                self.push_error(err);
+                true
+            }
+            Msg::RetryScheduled(id) => {
+                client.retry_scheduled(id);
+                ctx.link().send_message(Msg::PublishScheduled);
+                true
+            }
+            Msg::BroadcastedEvent(result) => {
+                match result {
+                    Ok(published) => {
+                        debug!("Broadcasted: {}", published.event_id);
+                        self.broadcasted_event = Some(published);
+                        // Show a reply in the thread it was posted to
+                        if let (View::Thread, Some(id)) = (&self.view, &self.thread_id) {
+                            ctx.link().send_message(Msg::OpenThread(id.clone()));
+                        }
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::Reply((id, text)) => {
+                let parent = match self.find_note(&id) {
+                    Some(parent) => parent,
+                    None => return false,
+                };
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::BroadcastedEvent(client.publish_reply(&text, &parent).await)
+                });
+                true
+            }
+            Msg::Quote((id, text)) => {
+                let quoted = match self.find_note(&id) {
+                    Some(quoted) => quoted,
+                    None => return false,
+                };
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::BroadcastedEvent(client.publish_quote(&text, &quoted).await)
+                });
+                true
+            }
+            Msg::OpenThread(id) => {
+                let event_id = match EventId::from_hex(id.as_str()) {
+                    Ok(event_id) => event_id,
+                    Err(_) => return false,
+                };
+                self.view = View::Thread;
+                self.thread_id = Some(id);
+                self.loading_thread = true;
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::ThreadLoaded(client.get_thread(event_id).await)
+                });
+                true
+            }
+            Msg::ThreadLoaded(result) => {
+                self.loading_thread = false;
+                match result {
+                    Ok(thread) => {
+                        let ids = thread.iter().map(|(_, event)| event.id).collect();
+                        ctx.link().send_message(Msg::LoadInteractions(ids));
+                        self.thread = thread;
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::React((id, reaction)) => {
+                let target = match self.find_note(&id) {
+                    Some(target) => target,
+                    None => return false,
+                };
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    let result = client.publish_reaction(&target, &reaction).await;
+                    Msg::InteractionPublished(target.id, result)
+                });
+                false
+            }
+            Msg::Repost(id) => {
+                let target = match self.find_note(&id) {
+                    Some(target) => target,
+                    None => return false,
+                };
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    let result = client.publish_repost(&target).await;
+                    Msg::InteractionPublished(target.id, result)
+                });
+                false
+            }
+            Msg::DeleteEvent(id) => {
+                let published = match client
+                    .get_published()
+                    .into_iter()
+                    .find(|published| published.event_id.to_hex() == id.as_str())
+                {
+                    Some(published) => published,
+                    None => return false,
+                };
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::BroadcastedEvent(client.delete_event(&published, "").await)
+                });
+                false
+            }
+            Msg::ProcessOutbox => {
+                if self.processing_outbox {
+                    return false;
+                }
+                self.processing_outbox = true;
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::OutboxProcessed(client.process_outbox().await)
+                });
+                false
+            }
+            Msg::OutboxProcessed(sent) => {
+                self.processing_outbox = false;
+                sent > 0 && matches!(self.view, View::History)
+            }
+            Msg::RetryOutbox(id) => match EventId::from_hex(id.as_str()) {
+                Ok(event_id) => {
+                    client.retry_outbox(&event_id);
+                    ctx.link().send_message(Msg::ProcessOutbox);
+                    true
+                }
+                Err(_) => false,
+            },
+            Msg::DiscardOutbox(id) => match EventId::from_hex(id.as_str()) {
+                Ok(event_id) => {
+                    client.discard_outbox(&event_id);
+                    true
+                }
+                Err(_) => false,
+            },
+            Msg::InteractionPublished(target, result) => {
+                match result {
+                    Ok(published) => {
+                        debug!("Broadcasted: {}", published.event_id);
+                        self.broadcasted_event = Some(published);
+                        ctx.link().send_message(Msg::LoadInteractions(vec![target]));
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::LoadInteractions(ids) => {
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::InteractionsLoaded(client.get_interactions(ids).await)
+                });
+                false
+            }
+            Msg::InteractionsLoaded(result) => match result {
+                Ok(events) => {
+                    self.interactions.insert(events);
+                    true
+                }
+                Err(err) => {
+                    // Counts are informational, the notes are still usable without them
+                    error!("Could not load reactions: {}", err);
+                    false
+                }
+            },
+            Msg::Delegate(conditions) => {
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::DelegationInfo(client.get_delegate(conditions).await)
+                });
+                true
+            }
+            Msg::DelegationSet(result) => {
+                match result {
+                    Ok(()) => debug!("Delegation set"),
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::DelegationInfo(result) => {
+                match result {
+                    Ok(delegation_info) => {
+                        if let Err(err) = client.add_delegation_info(delegation_info) {
+                            self.push_error(err);
+                            return true;
+                        }
+                        ctx.link().send_message(Msg::FetchMetadata);
+                        // Since there is now a delegation there is no need for remote signer
+                        let client = client.clone();
+                        self.send_session_future(ctx, async move {
+                            Msg::DelegationSet(client.create_client(DashSet::new()).await)
+                        });
+                    }
+                    Err(err) => self.push_error(err),
+                }
                 true
             }
-            Msg::DelegationSet => {
-                debug!("Delegation set");
-                // Since there is now a delegation there is no need for remote signer
-                self.client.create_client(DashSet::new()).ok();
+            Msg::ImportDelegation(token) => {
+                // Verified against the app pubkey when stored
+                match token {
+                    DelegationToken::Tag(tag) => ctx
+                        .link()
+                        .send_message(Msg::DelegationInfo(DelegationInfo::from_tag_json(&tag))),
+                    DelegationToken::Parts {
+                        delegator,
+                        conditions,
+                        signature,
+                    } => {
+                        // Delegator may be given as a NIP05 identifier
+                        let client = client.clone();
+                        self.send_session_future(ctx, async move {
+                            let delegation_info =
+                                client
+                                    .resolve_pubkey(&delegator)
+                                    .await
+                                    .and_then(|delegator| {
+                                        DelegationInfo::from_parts(
+                                            &delegator.to_string(),
+                                            &conditions,
+                                            &signature,
+                                        )
+                                    });
+                            Msg::DelegationInfo(delegation_info)
+                        });
+                    }
+                }
                 false
             }
-            Msg::DelegationInfo(delegation_info) => {
-                if self.client.set_delegation_info(delegation_info).is_err() {
-                    warn!("Could not set delegation info")
+            Msg::RemoveDelegation(signature) => {
+                match Signature::from_str(&signature) {
+                    Ok(signature) => {
+                        if let Err(err) = client.remove_delegation_info(&signature) {
+                            self.push_error(err);
+                        }
+                    }
+                    Err(_) => self
+                        .errors
+                        .push(format!("Invalid signature: {}", signature).into()),
                 }
                 true
             }
-            Msg::SetRemotePubkey(pubkey) => {
-                self.client.set_remote_pubkey(pubkey);
-                self.view = View::Home;
+            Msg::DelegationExpiry(DelegationExpiry::ExpiringSoon(delegation)) => {
+                let conditions = delegation.renewal_conditions(None);
+                if self.auto_renew {
+                    debug!("Renewing delegation: {}", conditions);
+                    ctx.link().send_message(Msg::Delegate(conditions));
+                } else {
+                    self.errors.push(ErrorBanner {
+                        message: format!(
+                            "Delegation expires at {}",
+                            delegation.created_before().unwrap_or_default()
+                        )
+                        .into(),
+                        renewal: Some(conditions),
+                    });
+                }
                 true
             }
+            Msg::DelegationExpiry(DelegationExpiry::Expired(delegation)) => {
+                let renewal = delegation.renewal_conditions(None);
+                if client.has_delegations() {
+                    // Signing does not fall back to the remote signer while delegations are held
+                    self.errors.push(ErrorBanner {
+                        message: "Delegation expired; request a new delegation".into(),
+                        renewal: Some(renewal),
+                    });
+                    return true;
+                }
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::RemoteSignerRestored(renewal, client.restore_remote_signer().await)
+                });
+                false
+            }
+            Msg::RemoteSignerRestored(renewal, result) => {
+                let message = match result {
+                    Ok(()) => "Delegation expired, publishing with the remote signer",
+                    Err(NostrServiceError::SignerNotSet) => {
+                        "Delegation expired; connect a signer or request a new delegation"
+                    }
+                    Err(err) => {
+                        debug!("Remote signer not restored: {err}");
+                        "Delegation expired; reconnect your signer or request a new delegation"
+                    }
+                };
+                self.errors.push(ErrorBanner {
+                    message: message.into(),
+                    renewal: Some(renewal),
+                });
+                true
+            }
+            Msg::SetAutoRenew(auto_renew) => {
+                self.auto_renew = auto_renew;
+                if let Err(err) = save_auto_renew(auto_renew) {
+                    self.push_error(err);
+                }
+                true
+            }
+            Msg::SetRemotePubkey(Err(NostrServiceError::SignerTimeout))
+                if matches!(self.view, View::Connect) && client.get_remote_signer().is_none() =>
+            {
+                // Pairing waits until the QR code is scanned, keep asking while it is shown
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::SetRemotePubkey(client.req_signer_pub_key().await)
+                });
+                false
+            }
+            Msg::SetRemotePubkey(result) => {
+                match result {
+                    Ok(pubkey) => {
+                        client.set_remote_pubkey(Some(pubkey));
+                        self.view = View::Home;
+                        self.timeline = None;
+                        ctx.link().send_message(Msg::FetchMetadata);
+                        ctx.link().send_message(Msg::LoadTimeline);
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::FetchMetadata => {
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::MetadataFetched(client.fetch_metadata().await)
+                });
+                false
+            }
+            Msg::MetadataFetched(result) => {
+                match result {
+                    Ok(()) => {
+                        let client = client.clone();
+                        self.send_session_future(ctx, async move {
+                            Msg::Nip05Verified(client.verify_nip05().await)
+                        });
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::Nip05Verified(verified) => {
+                self.nip05_verified = verified;
+                true
+            }
+            Msg::AddSigner(signer) => {
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::SetRemotePubkey(client.resolve_signer(&signer).await)
+                });
+                false
+            }
             Msg::Settings => {
                 self.view = View::Settings;
                 true
             }
+            Msg::Messages => {
+                self.view = View::Messages;
+                ctx.link().send_message(Msg::LoadMessages);
+                true
+            }
+            Msg::LoadMessages => {
+                self.loading_messages = true;
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::LoadedMessages(client.get_direct_messages().await)
+                });
+                true
+            }
+            Msg::LoadedMessages(result) => {
+                self.loading_messages = false;
+                match result {
+                    Ok(mut messages) => {
+                        // Keep what was decrypted before the refresh
+                        for message in messages.iter_mut() {
+                            message.content = self
+                                .messages
+                                .iter()
+                                .find(|known| known.event_id == message.event_id)
+                                .and_then(|known| known.content.clone());
+                        }
+                        self.messages = messages;
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::DecryptConversation(pubkey) => {
+                let messages: Vec<DirectMessage> = self
+                    .messages
+                    .iter()
+                    .filter(|message| message.counterparty.to_string() == pubkey.as_str())
+                    .cloned()
+                    .collect();
+                if messages.iter().all(|message| message.content.is_some()) {
+                    return false;
+                }
+                self.loading_messages = true;
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::ConversationDecrypted(client.decrypt_direct_messages(messages).await)
+                });
+                true
+            }
+            Msg::ConversationDecrypted(decrypted) => {
+                self.loading_messages = false;
+                for message in decrypted {
+                    if let Some(known) = self
+                        .messages
+                        .iter_mut()
+                        .find(|known| known.event_id == message.event_id)
+                    {
+                        known.content = message.content;
+                    }
+                }
+                true
+            }
+            Msg::SendMessage((receiver, text)) => {
+                match parse_pubkey(&receiver) {
+                    Some(receiver) => {
+                        let client = client.clone();
+                        self.send_session_future(ctx, async move {
+                            Msg::MessageSent(client.send_direct_message(receiver, &text).await)
+                        });
+                    }
+                    None => self
+                        .errors
+                        .push(format!("Invalid pubkey: {}", receiver).into()),
+                }
+                true
+            }
+            Msg::MessageSent(result) => {
+                match result {
+                    Ok(published) => {
+                        debug!("Sent message: {}", published.event_id);
+                        ctx.link().send_message(Msg::LoadMessages);
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
             Msg::Home => {
                 // If the app is not connected to a remote signer
                 // AND does not have a delegation tag
                 // Redirect to connect page
 
-                let delegation_info = match self.client.get_delegation_info() {
-                    Ok(Some(info)) => Some(info),
-                    _ => None,
-                };
-
-                let view = if self.client.get_remote_signer().is_none() && delegation_info.is_none()
-                {
+                let view = if client.get_remote_signer().is_none() && !client.has_delegations() {
                     View::Connect
                 } else {
+                    if self.timeline.is_none() {
+                        ctx.link().send_message(Msg::LoadTimeline);
+                    }
                     View::Home
                 };
                 self.view = view;
                 true
             }
-            Msg::LogOut => {
-                let keys = handle_keys(None, true).unwrap();
-                // Clear session
-                SessionStorage::clear();
-                self.client =
-                    NostrService::new(&keys, None, self.client.get_connect_relay()).unwrap();
+            Msg::LoadTimeline => {
+                if self.loading_notes {
+                    return false;
+                }
+                self.loading_notes = true;
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::TimelineLoaded(client.load_timeline().await)
+                });
+                true
+            }
+            Msg::TimelineLoaded(result) => {
+                self.loading_notes = false;
+                match result {
+                    Ok(timeline) => {
+                        ctx.link()
+                            .send_message(Msg::LoadInteractions(note_ids(timeline.notes())));
+                        self.timeline = Some(timeline);
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::LoadOlderNotes => {
+                let timeline = match &self.timeline {
+                    Some(timeline) if !self.loading_notes => timeline,
+                    _ => return false,
+                };
+                self.loading_notes = true;
+                let follows = timeline.follows().to_vec();
+                let until = timeline.oldest();
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::OlderNotesLoaded(client.get_notes(follows, None, until).await)
+                });
+                true
+            }
+            Msg::OlderNotesLoaded(result) => {
+                self.loading_notes = false;
+                match result {
+                    Ok(notes) => {
+                        ctx.link()
+                            .send_message(Msg::LoadInteractions(note_ids(&notes)));
+                        if let Some(timeline) = self.timeline.as_mut() {
+                            timeline.insert_older(notes);
+                        }
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::PollTimeline => {
+                let timeline = match &self.timeline {
+                    Some(timeline) if matches!(self.view, View::Home) && !self.loading_notes => {
+                        timeline
+                    }
+                    _ => return false,
+                };
+                let follows = timeline.follows().to_vec();
+                let since = timeline.newest();
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::NewerNotesLoaded(client.get_notes(follows, since, None).await)
+                });
+                false
+            }
+            Msg::NewerNotesLoaded(result) => match result {
+                Ok(notes) => match self.timeline.as_mut() {
+                    Some(timeline) => {
+                        let ids = note_ids(&notes);
+                        let added = timeline.insert(notes) > 0;
+                        if added {
+                            ctx.link().send_message(Msg::LoadInteractions(ids));
+                        }
+                        added
+                    }
+                    None => false,
+                },
+                Err(err) => {
+                    // Polled in the background, a failed poll is retried on the next tick
+                    error!("Could not poll timeline: {}", err);
+                    false
+                }
+            },
+            Msg::History => {
+                self.view = View::History;
+                true
+            }
+            Msg::Profile => {
+                self.view = View::Profile;
+                ctx.link().send_message(Msg::LoadProfile);
+                true
+            }
+            Msg::LoadProfile => {
+                self.profile = None;
+                self.profile_loaded = false;
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::ProfileLoaded(client.load_profile().await)
+                });
+                true
+            }
+            Msg::ProfileLoaded(result) => {
+                let metadata = match result {
+                    Ok(metadata) => metadata,
+                    Err(err) => {
+                        self.push_error(err);
+                        None
+                    }
+                };
+                self.profile_loaded = metadata.is_some();
+                // Still allow editing from the cached metadata, publishing it needs confirming
+                self.profile = metadata.or_else(|| {
+                    Some(
+                        client
+                            .profile_pubkey()
+                            .and_then(|pubkey| client.get_metadata(&pubkey))
+                            .unwrap_or_default(),
+                    )
+                });
+                true
+            }
+            Msg::PublishProfile(fields) => {
+                let metadata = fields.apply(self.profile.clone().unwrap_or_default());
+                self.publishing_profile = true;
+                let client = client.clone();
+                self.send_session_future(ctx, async move {
+                    Msg::ProfilePublished(client.publish_metadata(metadata).await)
+                });
+                true
+            }
+            Msg::ProfilePublished(result) => {
+                self.publishing_profile = false;
+                match result {
+                    Ok(published) => {
+                        debug!("Published profile: {}", published.event_id);
+                        self.profile = client
+                            .profile_pubkey()
+                            .and_then(|pubkey| client.get_metadata(&pubkey));
+                        self.profile_loaded = self.profile.is_some();
+                        self.broadcasted_event = Some(published);
+                    }
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::ExportBundle(password) => {
+                match client.export_bundle(&password) {
+                    Ok(bundle) => self.exported_bundle = Some(bundle.into()),
+                    Err(err) => self.push_error(err),
+                }
+                true
+            }
+            Msg::ImportBundle((bundle, password)) => {
+                // The password becomes the passphrase of the new account
+                if password.chars().count() < MIN_PASSPHRASE_LEN {
+                    self.push_error(NostrServiceError::PassphraseTooShort(MIN_PASSPHRASE_LEN));
+                    return true;
+                }
+                // Scheduled notes belong to the session that is replaced
+                let scheduled = client.get_scheduled().len();
+                if scheduled > 0 {
+                    self.push_error(NostrServiceError::ScheduledPending(scheduled));
+                    return true;
+                }
+                let root = match self.store.clone() {
+                    Some(root) => root,
+                    None => return false,
+                };
+                // Imported as a new account so the app key in use is kept
+                let account = self.accounts.create().clone();
+                let store: Store = Rc::new(namespaced(&root, &account));
 
-                self.view = View::Connect;
+                let imported =
+                    NostrService::import_bundle(&bundle, &password, store.clone(), account.clone());
+                // Switched to once the bundle is known to be written, even if the
+                // session ended meanwhile so the new account is never left half written
+                let flushed = store.flush();
+                ctx.link().send_future(async move {
+                    let imported = match imported {
+                        Ok(service) => match flushed.await {
+                            Ok(()) => Ok(service),
+                            Err(err) => {
+                                service.shutdown().await;
+                                Err(err)
+                            }
+                        },
+                        Err(err) => Err(err),
+                    };
+                    Msg::BundleImported(account, imported)
+                });
+                false
+            }
+            Msg::RenewDelegation(index) => {
+                if index < self.errors.len() {
+                    if let Some(conditions) = self.errors.remove(index).renewal {
+                        ctx.link().send_message(Msg::Delegate(conditions));
+                    }
+                }
                 true
             }
+            // Handled without a session in `update`
+            Msg::Unlock(_)
+            | Msg::Lock
+            | Msg::SwitchAccount(_)
+            | Msg::AddAccount
+            | Msg::LogOut
+            | Msg::SetStorageBackend(_)
+            | Msg::StoreOpened(..)
+            | Msg::StoreCopied(..)
+            | Msg::AppKeySaved(_)
+            | Msg::BundleImported(..)
+            | Msg::Session(..)
+            | Msg::DismissError(_) => false,
         }
     }
 
-    fn view(&self, ctx: &Context<Self>) -> Html {
-        let settings_cb = ctx.link().callback(|_| Msg::Settings);
-        let home_cb = ctx.link().callback(|_| Msg::Home);
+    /// Current view of an unlocked session
+    fn session_view(&self, ctx: &Context<Self>, client: &NostrService) -> Html {
+        match self.view {
+            View::Home => {
+                let note_cb = ctx.link().callback(Msg::SubmitNote);
+                let delegations = self.delegation_props(client);
 
-        let props = props! {
-            NavbarProps {
-                settings_cb,
-                home_cb
-            }
-        };
+                debug!("Delegator info: {:?}", delegations);
 
-        html! {
-        <>
-            {
+                let remote_signer = self.remote_signer_profile(client);
+                let more_notes_cb = ctx.link().callback(|_| Msg::LoadOlderNotes);
+                let props = props! {
+                    HomeProps {
+                        note_cb,
+                        schedule_cb: ctx.link().callback(Msg::ScheduleNote),
+                        scheduled: client
+                            .get_scheduled()
+                            .iter()
+                            .map(ScheduledProp::from)
+                            .collect::<Vec<_>>(),
+                        cancel_scheduled_cb: ctx.link().callback(Msg::CancelScheduled),
+                        retry_scheduled_cb: ctx.link().callback(Msg::RetryScheduled),
+                        delegations,
+                        remote_signer,
+                        notes: self
+                            .timeline
+                            .iter()
+                            .flat_map(|timeline| timeline.notes())
+                            .map(|event| self.note_prop(event))
+                            .collect::<Vec<_>>(),
+                        loading_notes: self.loading_notes,
+                        reply_cb: ctx.link().callback(Msg::Reply),
+                        quote_cb: ctx.link().callback(Msg::Quote),
+                        thread_cb: ctx.link().callback(Msg::OpenThread),
+                        react_cb: ctx.link().callback(Msg::React),
+                        repost_cb: ctx.link().callback(Msg::Repost),
+                        has_more_notes: self.timeline.as_ref().is_some_and(Timeline::has_more),
+                        more_notes_cb
+                    }
+                };
 
-            html! { <Navbar .. props />}
+                html! {
+                    <>
+                    if let Some(published) = &self.broadcasted_event {
+                        <p>{ format!("Broadcasted event: {}", published.event_id.to_hex())}</p>
+                        <p>{ format!("Accepted by {} of {} relays", published.accepted(), published.relays.len())}</p>
+                        <p>{ format!("Signed with: {}", published.signing_path)}</p>
+                    }
+                    <Home .. props />
+                    </>
+                }
             }
+            View::Connect => {
+                let connected_cb = ctx.link().callback(|_| Msg::Home);
+                let set_relay_cb = ctx.link().callback(Msg::AddRelay);
+                let add_signer_cb = ctx.link().callback(Msg::AddSigner);
+                let props = props! {
+                    ConnectProps {
+                        pubkey: client.get_app_pubkey().to_string(),
+                        connect_relay: client.get_connect_relay().to_string(),
+                        name: self.name.clone(),
+                        connected_cb,
+                        set_relay_cb,
+                        add_signer_cb
+                    }
+                };
 
-            {
-
-            match self.view {
-
-                View::Home => {
-                    let note_cb = ctx.link().callback(Msg::SubmitNote);
-                    let delegator = match self.client.get_delegation_info() {
-                        Ok(Some(info)) => Some(DelegationInfoProp::new(info.delegator_pubkey, info.created_after(), info.created_before(), info.kinds())),
-                        _ => None
-                    };
-
-                    debug!("Delegator info: {:?}", delegator);
-
-                    let remote_signer = self.client.get_remote_signer().map(|p| AttrValue::from(p.to_string()));
-
-                    html!{
+                html! { <Connect .. props /> }
+            }
+            View::Messages => {
+                let refresh_cb = ctx.link().callback(|_| Msg::LoadMessages);
+                let send_cb = ctx.link().callback(Msg::SendMessage);
+                let props = props! {
+                    MessagesProps {
+                        conversations: ConversationProp::from_messages(self.messages.clone()),
+                        loading: self.loading_messages,
+                        refresh_cb,
+                        decrypt_cb: ctx.link().callback(Msg::DecryptConversation),
+                        send_cb
+                    }
+                };
+                html! { <Messages .. props /> }
+            }
+            View::Profile => {
+                let save_cb = ctx.link().callback(Msg::PublishProfile);
+                let reload_cb = ctx.link().callback(|_| Msg::LoadProfile);
+                let props = props! {
+                    EditProfileProps {
+                        profile: self.profile.as_ref().map(ProfileFields::from),
+                        loaded: self.profile_loaded,
+                        saving: self.publishing_profile,
+                        save_cb,
+                        reload_cb
+                    }
+                };
+                html! {
                     <>
-                    if let Some(event_id) = &self.broadcasted_event {
-                        <p>{ format!("Broadcasted event: {}", event_id)}</p>
+                    if let Some(published) = &self.broadcasted_event {
+                        <p>{ format!("Broadcasted event: {}", published.event_id.to_hex())}</p>
+                        <p>{ format!("Accepted by {} of {} relays", published.accepted(), published.relays.len())}</p>
+                        <p>{ format!("Signed with: {}", published.signing_path)}</p>
                     }
-                    <Home {note_cb} {delegator} {remote_signer}/>
+                    <EditProfile .. props />
                     </>
                 }
-            },
-                View::Connect => {
-                    let connected_cb = ctx.link().callback(|_| Msg::Home);
-                    let set_relay_cb = ctx.link().callback(Msg::AddRelay);
-                    let props = props! {
-                        ConnectProps {
-                            pubkey: self.client.get_app_pubkey().to_string(),
-                            connect_relay: self.client.get_connect_relay().to_string(),
-                            name: self.name.clone(),
-                            connected_cb,
-                            set_relay_cb
-                        }
-                    };
-
-                    html! { <Connect .. props /> }
+            }
+            View::History => {
+                let props = props! {
+                    HistoryProps {
+                        published: client
+                            .get_published()
+                            .iter()
+                            .rev()
+                            .map(PublishedProp::from)
+                            .collect::<Vec<_>>(),
+                        delete_cb: ctx.link().callback(Msg::DeleteEvent),
+                        outbox: client
+                            .get_outbox()
+                            .iter()
+                            .map(OutboxProp::from)
+                            .collect::<Vec<_>>(),
+                        retry_cb: ctx.link().callback(Msg::RetryOutbox),
+                        discard_cb: ctx.link().callback(Msg::DiscardOutbox)
+                    }
+                };
+                html! { <History .. props /> }
+            }
+            View::Thread => {
+                let back_cb = ctx.link().callback(|_| Msg::Home);
+                let props = props! {
+                    ThreadProps {
+                        notes: self
+                            .thread
+                            .iter()
+                            .map(|(depth, event)| (*depth, self.note_prop(event)))
+                            .collect::<Vec<_>>(),
+                        loading: self.loading_thread,
+                        back_cb,
+                        reply_cb: ctx.link().callback(Msg::Reply),
+                        quote_cb: ctx.link().callback(Msg::Quote),
+                        thread_cb: ctx.link().callback(Msg::OpenThread),
+                        react_cb: ctx.link().callback(Msg::React),
+                        repost_cb: ctx.link().callback(Msg::Repost)
+                    }
+                };
+                html! {
+                    <>
+                    if let Some(published) = &self.broadcasted_event {
+                        <p>{ format!("Broadcasted event: {}", published.event_id.to_hex())}</p>
+                        <p>{ format!("Accepted by {} of {} relays", published.accepted(), published.relays.len())}</p>
+                    }
+                    <Thread .. props />
+                    </>
                 }
-                View::Settings => {
+            }
+            View::Settings => {
+                let delegation_cb = ctx.link().callback(Msg::Delegate);
+                let remove_delegation_cb = ctx.link().callback(Msg::RemoveDelegation);
+                let auto_renew_cb = ctx.link().callback(Msg::SetAutoRenew);
+                let import_delegation_cb = ctx.link().callback(Msg::ImportDelegation);
+                let export_bundle_cb = ctx.link().callback(Msg::ExportBundle);
+                let import_bundle_cb = ctx.link().callback(Msg::ImportBundle);
+                let storage_backend_cb = ctx.link().callback(Msg::SetStorageBackend);
 
-                    let delegation_info = match self.client.get_delegation_info() {
-                        Ok(Some(info)) => Some(DelegationInfoProp::new(info.delegator_pubkey, info.created_after(), info.created_before(), info.kinds())),
-                        _ => None
-                    };
-                    let delegation_cb = ctx.link().callback(Msg::Delegate);
-
-                    let update_connect_relay_cb = ctx.link().callback(Msg::UpdateConnectRelay);
-                    let add_relay_cb = ctx.link().callback(Msg::AddRelay);
-                    let logout_cb = ctx.link().callback(|_| Msg::LogOut);
-                    let remove_relay_cb = ctx.link().callback(Msg::RemoveRelay);
-                    let props = props! {
-                        SettingsProps {
-                            app_pubkey: self.client.get_app_pubkey().to_bech32().unwrap(),
-                            delegation_info: delegation_info,
-                            connect_relay: self.client.get_connect_relay().to_string(),
-                            relays: self.client.get_relays(),
-                            update_connect_relay_cb,
-                            add_relay_cb,
-                            logout_cb,
-                            remove_relay_cb,
-                            delegation_cb
-                        }
+                let update_connect_relay_cb = ctx.link().callback(Msg::UpdateConnectRelay);
+                let add_relay_cb = ctx.link().callback(Msg::AddRelay);
+                let logout_cb = ctx.link().callback(|_| Msg::LogOut);
+                let remove_relay_cb = ctx.link().callback(Msg::RemoveRelay);
+                let props = props! {
+                    SettingsProps {
+                        app_pubkey: client.get_app_pubkey().to_bech32().unwrap(),
+                        remote_signer: self.remote_signer_profile(client),
+                        delegations: self.delegation_props(client),
+                        connect_relay: client.get_connect_relay().to_string(),
+                        relays: client.get_relays(),
+                        update_connect_relay_cb,
+                        add_relay_cb,
+                        logout_cb,
+                        remove_relay_cb,
+                        delegation_cb,
+                        remove_delegation_cb,
+                        auto_renew: self.auto_renew,
+                        auto_renew_cb,
+                        import_delegation_cb,
+                        exported_bundle: self.exported_bundle.clone(),
+                        export_bundle_cb,
+                        import_bundle_cb,
+                        storage_backend: self.storage_backend,
+                        storage_backend_cb
+                    }
 
-                    };
-                    html! { <Settings .. props />}
-                }
+                };
+                html! { <Settings .. props />}
             }
         }
-        <footer class="footer">
-        </footer>
-        </>
+    }
+
+    /// Note shown in the timeline or thread with hex id
+    fn find_note(&self, id: &str) -> Option<Event> {
+        let timeline = self.timeline.iter().flat_map(|timeline| timeline.notes());
+        let thread = self.thread.iter().map(|(_, event)| event);
+        timeline
+            .chain(thread)
+            .find(|event| event.id.to_hex() == id)
+            .cloned()
+    }
+
+    /// Note with its reaction and repost counts as view props
+    fn note_prop(&self, event: &Event) -> NoteProp {
+        NoteProp::from(event).with_interactions(self.interactions.get(&event.id))
+    }
+
+    /// Log a service error and show it as a banner
+    fn push_error(&mut self, err: NostrServiceError) {
+        error!("{}", err);
+        let renewal = match &err {
+            NostrServiceError::DelegationUnsatisfied { renewal, .. } => Some(renewal.clone()),
+            _ => None,
+        };
+        self.errors.push(ErrorBanner {
+            message: err.to_string().into(),
+            renewal,
+        });
+    }
+
+    /// Delegations held by the app as view props
+    fn delegation_props(&self, client: &NostrService) -> Vec<DelegationInfoProp> {
+        match client.get_delegations() {
+            Ok(delegations) => delegations
+                .iter()
+                .map(|delegation| {
+                    let profile = ProfileProp::new(
+                        delegation.delegator_pubkey,
+                        client.get_metadata(&delegation.delegator_pubkey),
+                        self.nip05_verified.contains(&delegation.delegator_pubkey),
+                    );
+                    DelegationInfoProp::from(delegation).with_profile(profile)
+                })
+                .collect(),
+            Err(_) => vec![],
         }
     }
+
+    /// Metadata of the remote signer as view props
+    fn remote_signer_profile(&self, client: &NostrService) -> Option<ProfileProp> {
+        client.get_remote_signer().map(|pubkey| {
+            ProfileProp::new(
+                pubkey,
+                client.get_metadata(&pubkey),
+                self.nip05_verified.contains(&pubkey),
+            )
+        })
+    }
+}
+
+/// Ids of `notes`
+fn note_ids(notes: &[Event]) -> Vec<EventId> {
+    notes.iter().map(|note| note.id).collect()
+}
+
+/// Session state of `account` in `store`
+fn namespaced(store: &Store, account: &Account) -> NamespacedStore {
+    NamespacedStore::new(store.clone(), account)
 }
@@ -0,0 +1,42 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    pub message: AttrValue,
+    pub dismiss_cb: Callback<MouseEvent>,
+    /// Label of an optional action button
+    #[prop_or_default]
+    pub action: Option<AttrValue>,
+    #[prop_or_default]
+    pub action_cb: Callback<MouseEvent>,
+}
+
+pub struct Alert;
+
+impl Component for Alert {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+        <>
+        <div class="flex p-4 mb-4 text-red-800 rounded-lg bg-red-50 dark:bg-gray-800 dark:text-red-400" role="alert">
+            <div class="ml-3 text-sm font-medium">{ ctx.props().message.clone() }</div>
+            if let Some(action) = ctx.props().action.clone() {
+                <button type="button" class="ml-3 text-red-800 bg-transparent border border-red-800 hover:bg-red-900 hover:text-white focus:ring-4 focus:outline-none focus:ring-red-300 font-medium rounded-lg text-xs px-3 py-1.5 text-center dark:hover:bg-red-600 dark:border-red-600 dark:text-red-500 dark:hover:text-white dark:focus:ring-red-800" onclick={ctx.props().action_cb.clone()}>{ action }</button>
+            }
+            <button type="button" class="ml-auto -mx-1.5 -my-1.5 bg-red-50 text-red-500 rounded-lg focus:ring-2 focus:ring-red-400 p-1.5 hover:bg-red-200 inline-flex h-8 w-8 dark:bg-gray-800 dark:text-red-400 dark:hover:bg-gray-700" onclick={ctx.props().dismiss_cb.clone()}>
+                <span class="sr-only">{ "Dismiss" }</span>
+                <svg aria-hidden="true" class="w-5 h-5" fill="currentColor" viewBox="0 0 20 20" xmlns="http://www.w3.org/2000/svg">
+                    <path fill-rule="evenodd" d="M4.293 4.293a1 1 0 011.414 0L10 8.586l4.293-4.293a1 1 0 111.414 1.414L11.414 10l4.293 4.293a1 1 0 01-1.414 1.414L10 11.414l-4.293 4.293a1 1 0 01-1.414-1.414L8.586 10 4.293 5.707a1 1 0 010-1.414z" clip-rule="evenodd"></path>
+                </svg>
+            </button>
+        </div>
+        </>
+        }
+    }
+}
@@ -1,20 +1,45 @@
+use std::collections::BTreeSet;
+
 use log::debug;
+use nostr_sdk::prelude::{Condition, Conditions};
 use nostr_sdk::Timestamp;
+use wasm_bindgen::JsValue;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
+use crate::utils::from_datetime_local;
+
+/// Event kinds that can be picked for a delegation
+pub const KIND_CATALOGUE: &[(u64, &str)] = &[
+    (0, "Metadata"),
+    (1, "Text note"),
+    (3, "Contacts"),
+    (4, "Encrypted direct message"),
+    (5, "Event deletion"),
+    (6, "Repost"),
+    (7, "Reaction"),
+    (16, "Generic repost"),
+    (30023, "Long-form content"),
+];
+
+/// Default delegation length in seconds
+const DEFAULT_VALID_FOR: u64 = 24 * 60 * 60;
+
 #[derive(Properties, PartialEq, Default, Clone)]
 pub struct Props {
-    pub delegate_cb: Callback<(u64, Vec<u64>)>,
+    pub delegate_cb: Callback<Conditions>,
 }
 
 pub struct Delegate {
-    for_hours: NodeRef,
-    for_min: NodeRef,
-    // kinds: NodeRef,
+    kinds: BTreeSet<u64>,
+    created_after: Option<u64>,
+    created_before: Option<u64>,
 }
 
 pub enum Msg {
+    ToggleKind(u64),
+    SetCreatedAfter(Option<u64>),
+    SetCreatedBefore(Option<u64>),
     Delegate,
 }
 
@@ -23,53 +48,137 @@ impl Component for Delegate {
     type Properties = Props;
 
     fn create(_ctx: &Context<Self>) -> Self {
+        let now = Timestamp::now().as_u64();
         Self {
-            for_hours: NodeRef::default(),
-            for_min: NodeRef::default(),
-            // kinds: NodeRef::default(),
+            kinds: BTreeSet::from([1]),
+            created_after: Some(now),
+            created_before: Some(now + DEFAULT_VALID_FOR),
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
+            Msg::ToggleKind(kind) => {
+                if !self.kinds.remove(&kind) {
+                    self.kinds.insert(kind);
+                }
+            }
+            Msg::SetCreatedAfter(time) => self.created_after = time,
+            Msg::SetCreatedBefore(time) => self.created_before = time,
             Msg::Delegate => {
                 debug!("Delegate");
-                if let (Some(hours), Some(mins)) = (
-                    self.for_hours.cast::<HtmlInputElement>(),
-                    self.for_min.cast::<HtmlInputElement>(),
-                ) {
-                    let hours = hours.value_as_number();
-                    let mins = mins.value_as_number();
-
-                    let expiration =
-                        Timestamp::now().as_u64() + (hours as u64 * 3600) + (mins as u64 * 60);
-                    ctx.props().delegate_cb.emit((expiration, vec![1]));
+                if self.validate().is_ok() {
+                    ctx.props().delegate_cb.emit(self.conditions());
                 }
-
-                true
             }
         }
+
+        true
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let onclick = ctx.link().callback(|_| Msg::Delegate);
+        let on_after = ctx.link().callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::SetCreatedAfter(from_datetime_local(&input.value()))
+        });
+        let on_before = ctx.link().callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::SetCreatedBefore(from_datetime_local(&input.value()))
+        });
+
+        let validation = self.validate();
+
         html! {
             <>
-            <p class="tracking-tight text-gray-500 md:text-lg dark:text-gray-400">{ "Create a delegation token, valid for: " }</p>
-              <div class="flex">
-                  <div class="relative z-0 p-4 mb-6 group">
-                      <input type="number" name="floating_hours" id="floating_hours" class="block py-2.5 px-0 w-full text-sm text-gray-900 bg-transparent border-0 border-b-2 border-gray-300 appearance-none dark:text-white dark:border-gray-600 dark:focus:border-blue-500 focus:outline-none focus:ring-0 focus:border-blue-600 peer" placeholder=" " ref={self.for_hours.clone()}/>
-                      <label for="floating_hours" class="peer-focus:font-medium absolute text-sm text-gray-500 dark:text-gray-400 duration-300 transform -translate-y-6 scale-75 top-3 -z-10 origin-[0] peer-focus:left-0 peer-focus:text-blue-600 peer-focus:dark:text-blue-500 peer-placeholder-shown:scale-100 peer-placeholder-shown:translate-y-0 peer-focus:scale-75 peer-focus:-translate-y-6">{"Hours"}</label>
-                  </div>
-                   <div class="relative z-0 p-4 mb-6 group">
-                      <input type="number" name="floating_mins" id="floating_mins" class="block py-2.5 px-0 w-full text-sm text-gray-900 bg-transparent border-0 border-b-2 border-gray-300 appearance-none dark:text-white dark:border-gray-600 dark:focus:border-blue-500 focus:outline-none focus:ring-0 focus:border-blue-600 peer" placeholder=" " ref={self.for_min.clone()}/>
-                      <label for="floating_mins" class="peer-focus:font-medium absolute text-sm text-gray-500 dark:text-gray-400 duration-300 transform -translate-y-6 scale-75 top-3 -z-10 origin-[0] peer-focus:left-0 peer-focus:text-blue-600 peer-focus:dark:text-blue-500 peer-placeholder-shown:scale-100 peer-placeholder-shown:translate-y-0 peer-focus:scale-75 peer-focus:-translate-y-6">{"Minutes"}</label>
-                  </div>
-                   <div class="relative z-0 p-4 mb-6 group">
-                        <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" {onclick}>{ "Delegate" } </button>
-                    </div>
+            <p class="tracking-tight text-gray-500 md:text-lg dark:text-gray-400">{ "Create a delegation token" }</p>
+
+            // Kinds the delegation is valid for
+            <div class="flex flex-wrap p-4">
+                {
+                    KIND_CATALOGUE.iter().map(|(kind, name)| {
+                        let kind = *kind;
+                        let id = format!("kind-{}", kind);
+                        let onchange = ctx.link().callback(move |_| Msg::ToggleKind(kind));
+                        html! {
+                            <div class="flex items-center mr-4 mb-2">
+                                <input type="checkbox" id={id.clone()} class="w-4 h-4 text-purple-600 bg-gray-100 border-gray-300 rounded focus:ring-purple-500 dark:focus:ring-purple-600 dark:ring-offset-gray-800 focus:ring-2 dark:bg-gray-700 dark:border-gray-600" checked={self.kinds.contains(&kind)} {onchange}/>
+                                <label for={id} class="ml-2 text-sm font-medium text-gray-900 dark:text-gray-300">{ format!("{} ({})", name, kind) }</label>
+                            </div>
+                        }
+                    }).collect::<Html>()
+                }
+            </div>
+
+            // Validity window
+            <div class="flex">
+                <div class="p-4 mb-6">
+                    <label for="created_after" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Valid from" }</label>
+                    <input type="datetime-local" id="created_after" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" value={self.created_after.map(to_datetime_local).unwrap_or_default()} onchange={on_after}/>
                 </div>
+                <div class="p-4 mb-6">
+                    <label for="created_before" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Valid until" }</label>
+                    <input type="datetime-local" id="created_before" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" value={self.created_before.map(to_datetime_local).unwrap_or_default()} onchange={on_before}/>
+                </div>
+            </div>
+
+            // Preview of NIP26 conditions query string
+            <p class="text-sm text-gray-500 dark:text-gray-400">{ "Conditions" }</p>
+            <code class="block p-2 mb-2 text-sm break-all bg-gray-100 rounded-lg dark:bg-gray-800">{ self.conditions().to_string() }</code>
+
+            if let Err(reason) = validation {
+                <p class="text-sm text-red-600 dark:text-red-400">{ reason }</p>
+            }
+
+            <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" disabled={validation.is_err()} {onclick}>{ "Delegate" } </button>
             </>
         }
     }
 }
+
+impl Delegate {
+    /// NIP26 conditions from the selected kinds and validity window
+    fn conditions(&self) -> Conditions {
+        let mut conditions = Conditions::new();
+
+        for kind in &self.kinds {
+            conditions.add(Condition::Kind(*kind));
+        }
+        if let Some(time) = self.created_after {
+            conditions.add(Condition::CreatedAfter(time));
+        }
+        if let Some(time) = self.created_before {
+            conditions.add(Condition::CreatedBefore(time));
+        }
+
+        conditions
+    }
+
+    /// Check the builder holds conditions worth requesting
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.kinds.is_empty() {
+            return Err("Select at least one kind");
+        }
+
+        match (self.created_after, self.created_before) {
+            (Some(after), Some(before)) if after >= before => {
+                Err("Valid from must be before valid until")
+            }
+            (_, None) => Err("Set when the delegation expires"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Format unix time as a `datetime-local` input value in local time
+fn to_datetime_local(time: u64) -> String {
+    let date = js_sys::Date::new(&JsValue::from_f64(time as f64 * 1000.0));
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
@@ -1,2 +1,7 @@
+pub mod alert;
 pub mod delegate;
 pub mod navbar;
+pub mod note;
+pub mod outbox;
+pub mod profile;
+pub mod published;
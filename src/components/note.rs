@@ -0,0 +1,220 @@
+use nostr_sdk::{nips::nip19::ToBech32, Event};
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::services::interactions::{InteractionCounts, Reaction};
+use crate::utils::format_timestamp;
+
+/// Text note ready to render
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct NoteProp {
+    /// Hex event id
+    pub id: AttrValue,
+    /// Bech32 pubkey of the author
+    pub author: AttrValue,
+    pub content: AttrValue,
+    /// Creation time in the browser locale
+    pub created_at: AttrValue,
+    /// Reaction content and count, most sent first
+    pub reactions: Vec<(AttrValue, usize)>,
+    pub reposts: usize,
+}
+
+impl From<&Event> for NoteProp {
+    fn from(event: &Event) -> Self {
+        let author = match event.pubkey.to_bech32() {
+            Ok(key) => key.into(),
+            Err(_) => event.pubkey.to_string().into(),
+        };
+        Self {
+            id: event.id.to_hex().into(),
+            author,
+            content: event.content.clone().into(),
+            created_at: format_timestamp(event.created_at.as_u64()).into(),
+            reactions: vec![],
+            reposts: 0,
+        }
+    }
+}
+
+impl NoteProp {
+    pub fn with_interactions(mut self, counts: Option<&InteractionCounts>) -> Self {
+        if let Some(counts) = counts {
+            let mut reactions: Vec<(AttrValue, usize)> = counts
+                .reactions
+                .iter()
+                .map(|(content, count)| (content.clone().into(), *count))
+                .collect();
+            reactions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            self.reactions = reactions;
+            self.reposts = counts.reposts;
+        }
+        self
+    }
+
+    /// Number of `+` reactions
+    fn likes(&self) -> usize {
+        self.reactions
+            .iter()
+            .find(|(content, _)| content == "+")
+            .map_or(0, |(_, count)| *count)
+    }
+}
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    pub note: NoteProp,
+    /// Note id and reply text
+    pub reply_cb: Callback<(AttrValue, AttrValue)>,
+    /// Note id and quoting text
+    pub quote_cb: Callback<(AttrValue, AttrValue)>,
+    /// Note id
+    pub thread_cb: Callback<AttrValue>,
+    /// Note id and reaction
+    pub react_cb: Callback<(AttrValue, Reaction)>,
+    /// Note id
+    pub repost_cb: Callback<AttrValue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compose {
+    Reply,
+    Quote,
+    React,
+}
+
+pub enum Msg {
+    Like,
+    Repost,
+    Compose(Compose),
+    Cancel,
+    Submit,
+}
+
+pub struct Note {
+    compose: Option<Compose>,
+    text: NodeRef,
+    emoji: NodeRef,
+    emoji_url: NodeRef,
+}
+
+impl Component for Note {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            compose: None,
+            text: NodeRef::default(),
+            emoji: NodeRef::default(),
+            emoji_url: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Like => {
+                let id = ctx.props().note.id.clone();
+                ctx.props().react_cb.emit((id, Reaction::Like));
+                false
+            }
+            Msg::Repost => {
+                ctx.props().repost_cb.emit(ctx.props().note.id.clone());
+                false
+            }
+            Msg::Compose(compose) => {
+                self.compose = Some(compose);
+                true
+            }
+            Msg::Cancel => {
+                self.compose = None;
+                true
+            }
+            Msg::Submit if self.compose == Some(Compose::React) => {
+                let value = |node_ref: &NodeRef| {
+                    node_ref
+                        .cast::<HtmlInputElement>()
+                        .map(|input| input.value())
+                        .unwrap_or_default()
+                };
+                let url = value(&self.emoji_url);
+                let reaction = match Reaction::parse(&value(&self.emoji), Some(&url)) {
+                    Some(reaction) => reaction,
+                    None => return false,
+                };
+
+                self.compose = None;
+                ctx.props()
+                    .react_cb
+                    .emit((ctx.props().note.id.clone(), reaction));
+                true
+            }
+            Msg::Submit => {
+                let text = match self.text.cast::<HtmlTextAreaElement>() {
+                    Some(input) => input.value(),
+                    None => return false,
+                };
+                if text.trim().is_empty() {
+                    return false;
+                }
+
+                let id = ctx.props().note.id.clone();
+                match self.compose.take() {
+                    Some(Compose::Reply) => ctx.props().reply_cb.emit((id, text.into())),
+                    Some(Compose::Quote) => ctx.props().quote_cb.emit((id, text.into())),
+                    Some(Compose::React) | None => {}
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let note = &ctx.props().note;
+        let id = note.id.clone();
+        let thread_cb = ctx.props().thread_cb.clone();
+        let show_thread = Callback::from(move |_| thread_cb.emit(id.clone()));
+
+        html! {
+            <div class="py-3">
+                <p class="text-sm font-medium text-gray-900 truncate dark:text-white">{ note.author.clone() }</p>
+                <p class="text-xs text-gray-500 dark:text-gray-400">{ note.created_at.clone() }</p>
+                <p class="text-base text-gray-900 whitespace-pre-wrap break-words dark:text-white">{ note.content.clone() }</p>
+                // Reactions other than likes
+                if note.reactions.iter().any(|(content, _)| content != "+") {
+                    <div class="flex flex-wrap gap-1 mt-1">
+                        {
+                            note.reactions.iter().filter(|(content, _)| content != "+").map(|(content, count)| html! {
+                                <span class="text-xs bg-gray-100 text-gray-800 font-medium px-2 py-0.5 rounded dark:bg-gray-700 dark:text-gray-300">{ format!("{} {}", content, count) }</span>
+                            }).collect::<Html>()
+                        }
+                    </div>
+                }
+                <div class="flex space-x-4 mt-1 text-sm font-medium text-purple-700 dark:text-purple-400">
+                    <button type="button" class="hover:underline" onclick={ctx.link().callback(|_| Msg::Compose(Compose::Reply))}>{ "Reply" }</button>
+                    <button type="button" class="hover:underline" onclick={ctx.link().callback(|_| Msg::Compose(Compose::Quote))}>{ "Quote" }</button>
+                    <button type="button" class="hover:underline" onclick={ctx.link().callback(|_| Msg::Repost)}>{ format!("Repost {}", note.reposts) }</button>
+                    <button type="button" class="hover:underline" onclick={ctx.link().callback(|_| Msg::Like)}>{ format!("Like {}", note.likes()) }</button>
+                    <button type="button" class="hover:underline" onclick={ctx.link().callback(|_| Msg::Compose(Compose::React))}>{ "React" }</button>
+                    <button type="button" class="hover:underline" onclick={show_thread}>{ "Thread" }</button>
+                </div>
+                if self.compose == Some(Compose::React) {
+                    <div class="mt-2">
+                        <input type="text" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 mb-2 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="Emoji or :shortcode:" ref={self.emoji.clone()}/>
+                        <input type="text" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 mb-2 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="Custom emoji image URL (optional)" ref={self.emoji_url.clone()}/>
+                        <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 mr-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::Submit)}>{ "React" }</button>
+                        <button type="button" class="text-sm font-medium text-gray-500 hover:underline dark:text-gray-400" onclick={ctx.link().callback(|_| Msg::Cancel)}>{ "Cancel" }</button>
+                    </div>
+                } else if let Some(compose) = self.compose {
+                    <div class="mt-2">
+                        <textarea rows="3" class="block p-2.5 mb-2 w-full text-sm text-gray-900 bg-gray-50 rounded-lg border border-gray-300 focus:ring-blue-500 focus:border-blue-500 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder={ if compose == Compose::Reply { "Write a reply..." } else { "Add a comment..." } } ref={self.text.clone()}></textarea>
+                        <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 mr-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::Submit)}>
+                            { if compose == Compose::Reply { "Reply" } else { "Quote" } }
+                        </button>
+                        <button type="button" class="text-sm font-medium text-gray-500 hover:underline dark:text-gray-400" onclick={ctx.link().callback(|_| Msg::Cancel)}>{ "Cancel" }</button>
+                    </div>
+                }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use yew::prelude::*;
+
+use crate::services::outbox::{OutboxItem, PendingRelay};
+use crate::utils::format_timestamp;
+
+/// Event waiting in the outbox ready to render
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct OutboxProp {
+    /// Hex event id
+    pub id: AttrValue,
+    pub kind: u64,
+    pub signing_path: AttrValue,
+    /// Signing time in the browser locale
+    pub created_at: AttrValue,
+    pub relays: Vec<PendingRelay>,
+    /// No relay is retried automatically anymore
+    pub failed: bool,
+}
+
+impl From<&OutboxItem> for OutboxProp {
+    fn from(item: &OutboxItem) -> Self {
+        Self {
+            id: item.event.id.to_hex().into(),
+            kind: item.event.kind.as_u64(),
+            signing_path: item.signing_path.to_string().into(),
+            created_at: format_timestamp(item.event.created_at.as_u64()).into(),
+            relays: item.relays.clone(),
+            failed: item.failed(),
+        }
+    }
+}
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    pub outbox: Vec<OutboxProp>,
+    /// Event id
+    pub retry_cb: Callback<AttrValue>,
+    /// Event id
+    pub discard_cb: Callback<AttrValue>,
+}
+
+pub struct OutboxList;
+
+impl Component for OutboxList {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <ul class="max-w-md divide-y divide-gray-200 dark:divide-gray-700">
+                {
+                    ctx.props().outbox.iter().map(|item| {
+                        let id = item.id.clone();
+                        let retry_cb = ctx.props().retry_cb.clone();
+                        let retry = Callback::from(move |_| retry_cb.emit(id.clone()));
+                        let id = item.id.clone();
+                        let discard_cb = ctx.props().discard_cb.clone();
+                        let discard = Callback::from(move |_| discard_cb.emit(id.clone()));
+                        html! {
+                            <li class="py-3" key={item.id.as_str()}>
+                                <p class="text-sm font-medium text-gray-900 truncate dark:text-white">{ item.id.clone() }</p>
+                                <p class="text-xs text-gray-500 dark:text-gray-400">
+                                    { format!("Kind {} signed with {} at {}", item.kind, item.signing_path, item.created_at) }
+                                </p>
+                                if item.failed {
+                                    <span class="text-xs bg-red-100 text-red-800 font-medium px-2 py-0.5 rounded dark:bg-red-900 dark:text-red-300">{ "Failed" }</span>
+                                } else {
+                                    <span class="text-xs bg-yellow-100 text-yellow-800 font-medium px-2 py-0.5 rounded dark:bg-yellow-900 dark:text-yellow-300">{ "Pending" }</span>
+                                }
+                                <ul class="my-1">
+                                    {
+                                        item.relays.iter().map(|pending| {
+                                            let next = if pending.failed() {
+                                                "retry by hand".to_string()
+                                            } else {
+                                                format!("next try at {}", format_timestamp(pending.next_attempt))
+                                            };
+                                            html! {
+                                                <li class="text-xs text-red-700 dark:text-red-400">
+                                                    { format!("{} failed {} times, {}: {}", pending.relay, pending.attempts, next, pending.message) }
+                                                </li>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                </ul>
+                                <div class="flex space-x-4 text-sm font-medium">
+                                    <button type="button" class="text-purple-700 hover:underline dark:text-purple-400" onclick={retry}>{ "Retry" }</button>
+                                    <button type="button" class="text-red-700 hover:underline dark:text-red-500" onclick={discard}>{ "Discard" }</button>
+                                </div>
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+        }
+    }
+}
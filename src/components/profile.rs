@@ -0,0 +1,85 @@
+use nostr_sdk::{nips::nip19::ToBech32, secp256k1::XOnlyPublicKey, Metadata};
+use yew::prelude::*;
+
+/// Kind 0 metadata of a pubkey ready to render
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ProfileProp {
+    /// npub of the profile
+    pub pubkey: AttrValue,
+    pub name: Option<AttrValue>,
+    pub picture: Option<AttrValue>,
+    pub about: Option<AttrValue>,
+    pub nip05: Option<AttrValue>,
+    /// NIP05 identifier resolves to the pubkey
+    pub verified: bool,
+}
+
+impl ProfileProp {
+    pub fn new(pubkey: XOnlyPublicKey, metadata: Option<Metadata>, verified: bool) -> Self {
+        let npub = match pubkey.to_bech32() {
+            Ok(key) => key.into(),
+            Err(_) => pubkey.to_string().into(),
+        };
+        let metadata = metadata.unwrap_or_default();
+
+        Self {
+            pubkey: npub,
+            name: metadata
+                .display_name
+                .filter(|name| !name.is_empty())
+                .or(metadata.name)
+                .map(AttrValue::from),
+            picture: metadata.picture.map(AttrValue::from),
+            about: metadata.about.map(AttrValue::from),
+            nip05: metadata.nip05.map(AttrValue::from),
+            verified,
+        }
+    }
+}
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    pub profile: ProfileProp,
+}
+
+pub struct Profile;
+
+impl Component for Profile {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let profile = &ctx.props().profile;
+
+        html! {
+        <div class="flex items-center space-x-4 p-3">
+            if let Some(picture) = profile.picture.clone() {
+                <img class="w-10 h-10 rounded-full" src={picture} alt=""/>
+            } else {
+                <div class="w-10 h-10 rounded-full bg-gray-200 dark:bg-gray-600"></div>
+            }
+            <div class="font-medium dark:text-white">
+                <div>{ profile.name.clone().unwrap_or_else(|| "Unknown".into()) }</div>
+                if let Some(nip05) = profile.nip05.clone() {
+                    <div class="flex items-center text-sm text-purple-700 dark:text-purple-400">
+                        { nip05 }
+                        if profile.verified {
+                            <svg class="w-4 h-4 ml-1" fill="currentColor" viewBox="0 0 20 20" xmlns="http://www.w3.org/2000/svg" aria-label="Verified">
+                                <path fill-rule="evenodd" d="M6.267 3.455a3.066 3.066 0 001.745-.723 3.066 3.066 0 013.976 0 3.066 3.066 0 001.745.723 3.066 3.066 0 012.812 2.812c.051.643.304 1.254.723 1.745a3.066 3.066 0 010 3.976 3.066 3.066 0 00-.723 1.745 3.066 3.066 0 01-2.812 2.812 3.066 3.066 0 00-1.745.723 3.066 3.066 0 01-3.976 0 3.066 3.066 0 00-1.745-.723 3.066 3.066 0 01-2.812-2.812 3.066 3.066 0 00-.723-1.745 3.066 3.066 0 010-3.976 3.066 3.066 0 00.723-1.745 3.066 3.066 0 012.812-2.812zm7.44 5.252a1 1 0 00-1.414-1.414L9 10.586 7.707 9.293a1 1 0 00-1.414 1.414l2 2a1 1 0 001.414 0l4-4z" clip-rule="evenodd"></path>
+                            </svg>
+                        }
+                    </div>
+                }
+                <div class="text-sm text-gray-500 dark:text-gray-400 break-all">{ profile.pubkey.clone() }</div>
+                if let Some(about) = profile.about.clone() {
+                    <div class="text-sm font-normal text-gray-700 dark:text-gray-300">{ about }</div>
+                }
+            </div>
+        </div>
+        }
+    }
+}
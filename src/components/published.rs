@@ -0,0 +1,110 @@
+use nostr_sdk::{nips::nip19::ToBech32, Kind};
+use yew::prelude::*;
+
+use crate::services::nostr::{PublishedEvent, RelayStatus};
+use crate::utils::format_timestamp;
+
+/// Event published by the app ready to render
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct PublishedProp {
+    /// Hex event id
+    pub id: AttrValue,
+    pub kind: u64,
+    pub signing_path: AttrValue,
+    /// Publish time in the browser locale
+    pub created_at: AttrValue,
+    /// Bech32 pubkey of the delegator of the delegation used
+    pub delegator: Option<AttrValue>,
+    pub relays: Vec<RelayStatus>,
+    /// A deletion request was published for the event
+    pub deleted: bool,
+}
+
+impl From<&PublishedEvent> for PublishedProp {
+    fn from(published: &PublishedEvent) -> Self {
+        let delegator = published.delegator.map(|pubkey| match pubkey.to_bech32() {
+            Ok(key) => key.into(),
+            Err(_) => pubkey.to_string().into(),
+        });
+
+        Self {
+            id: published.event_id.to_hex().into(),
+            kind: published.kind.as_u64(),
+            signing_path: published.signing_path.to_string().into(),
+            created_at: format_timestamp(published.created_at.as_u64()).into(),
+            delegator,
+            relays: published.relays.clone(),
+            deleted: published.deletion.is_some(),
+        }
+    }
+}
+
+impl PublishedProp {
+    /// Deletions can not be deleted themselves
+    fn deletable(&self) -> bool {
+        !self.deleted && self.kind != Kind::EventDeletion.as_u64()
+    }
+}
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    /// Newest first
+    pub published: Vec<PublishedProp>,
+    /// Event id
+    pub delete_cb: Callback<AttrValue>,
+}
+
+pub struct PublishedList;
+
+impl Component for PublishedList {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <ul class="max-w-md divide-y divide-gray-200 dark:divide-gray-700">
+                {
+                    ctx.props().published.iter().map(|published| {
+                        let id = published.id.clone();
+                        let delete_cb = ctx.props().delete_cb.clone();
+                        let onclick = Callback::from(move |_| delete_cb.emit(id.clone()));
+                        html! {
+                            <li class="py-3" key={published.id.as_str()}>
+                                <p class="text-sm font-medium text-gray-900 truncate dark:text-white">{ published.id.clone() }</p>
+                                <p class="text-xs text-gray-500 dark:text-gray-400">
+                                    { format!("Kind {} signed with {} at {}", published.kind, published.signing_path, published.created_at) }
+                                </p>
+                                if let Some(delegator) = published.delegator.clone() {
+                                    <p class="text-xs text-gray-500 truncate dark:text-gray-400">{ format!("Delegated by {}", delegator) }</p>
+                                }
+                                <ul class="my-1">
+                                    {
+                                        published.relays.iter().map(|status| {
+                                            let (class, answer) = if status.accepted {
+                                                ("text-xs text-green-700 dark:text-green-400", "OK".to_string())
+                                            } else {
+                                                ("text-xs text-red-700 dark:text-red-400", format!("Rejected: {}", status.message))
+                                            };
+                                            html! {
+                                                <li {class}>{ format!("{} {}", status.relay, answer) }</li>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                </ul>
+                                if published.deleted {
+                                    <span class="text-xs bg-red-100 text-red-800 font-medium px-2 py-0.5 rounded dark:bg-red-900 dark:text-red-300">{ "Deletion requested" }</span>
+                                } else if published.deletable() {
+                                    <button type="button" class="text-sm font-medium text-red-700 hover:underline dark:text-red-500" {onclick}>{ "Delete" }</button>
+                                }
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use nostr_sdk::{secp256k1::XOnlyPublicKey, Keys};
+use serde::{Deserialize, Serialize};
+
+use super::error::NostrServiceError;
+use super::nip49;
+use super::nostr::DelegationInfo;
+
+/// Current version of the session bundle schema
+const BUNDLE_VERSION: u32 = 1;
+
+/// Portable connected session, the app key is NIP49 encrypted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionBundle {
+    pub version: u32,
+    /// NIP49 encrypted app secret key
+    pub ncryptsec: String,
+    pub remote_signer: Option<XOnlyPublicKey>,
+    pub connect_relay: String,
+    pub relays: Vec<String>,
+    pub delegations: Vec<DelegationInfo>,
+}
+
+impl SessionBundle {
+    /// Bundle a session, encrypting the app key with `password`
+    pub fn new(
+        keys: &Keys,
+        password: &str,
+        remote_signer: Option<XOnlyPublicKey>,
+        connect_relay: String,
+        relays: Vec<String>,
+        delegations: Vec<DelegationInfo>,
+    ) -> Result<Self, NostrServiceError> {
+        let ncryptsec = nip49::encrypt(&keys.secret_key()?, password, nip49::DEFAULT_LOG_N)?;
+
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            ncryptsec,
+            remote_signer,
+            connect_relay,
+            relays,
+            delegations,
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, NostrServiceError> {
+        let bundle: Self = serde_json::from_str(json.trim())?;
+        if bundle.version != BUNDLE_VERSION {
+            return Err(NostrServiceError::UnsupportedVersion(bundle.version));
+        }
+
+        Ok(bundle)
+    }
+
+    pub fn to_json(&self) -> Result<String, NostrServiceError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decrypt the app keys with `password`
+    pub fn keys(&self, password: &str) -> Result<Keys, NostrServiceError> {
+        let secret_key = nip49::decrypt(&self.ncryptsec, password)?;
+        Ok(Keys::new(secret_key))
+    }
+}
@@ -0,0 +1,116 @@
+use gloo::storage::errors::StorageError;
+use nostr_sdk::client::Error as ClientError;
+use nostr_sdk::nips::nip26::Conditions;
+use thiserror::Error;
+
+use super::nip05::Nip05Error;
+use super::nip49::Nip49Error;
+use super::nostr::EncryptionScheme;
+
+/// Errors returned by [`NostrService`](super::nostr::NostrService)
+#[derive(Debug, Error)]
+pub enum NostrServiceError {
+    /// Relay or relay pool failure
+    #[error("Relay error: {0}")]
+    Relay(#[from] ClientError),
+    /// Remote signer did not answer in time
+    #[error("Remote signer did not respond in time")]
+    SignerTimeout,
+    /// Remote signer refused or failed the request
+    #[error("Remote signer rejected the request: {0}")]
+    SignerRejected(String),
+    /// Signer sent a response that does not match the request
+    #[error("Unexpected response from remote signer")]
+    UnexpectedResponse,
+    /// No remote signer pubkey is known
+    #[error("Remote signer pubkey not set")]
+    SignerNotSet,
+    /// Remote signer request not supported by this client
+    #[error("{0} is not supported by the remote signer client")]
+    Unsupported(EncryptionScheme),
+    /// Delegation is missing, expired or has a bad signature
+    #[error("Invalid delegation: {0}")]
+    DelegationInvalid(String),
+    /// No held delegation covers the event being published
+    #[error("No delegation covers kind {kind}: {}", join_reasons(.reasons))]
+    DelegationUnsatisfied {
+        kind: u64,
+        reasons: Vec<DelegationError>,
+        /// Conditions to request a delegation that would cover the event
+        renewal: Conditions,
+    },
+    /// Key error
+    #[error("Key error: {0}")]
+    Keys(#[from] nostr_sdk::nostr::key::Error),
+    /// Event could not be built or signed
+    #[error("Event error: {0}")]
+    Event(#[from] nostr_sdk::nostr::event::builder::Error),
+    /// No publish relay is set, an event would reach nobody
+    #[error("No relays to publish to, add one in settings")]
+    NoRelays,
+    /// Passphrase shorter than a new app key needs
+    #[error("Passphrase must be at least {0} characters")]
+    PassphraseTooShort(usize),
+    /// Scheduled notes are kept by the session only and would be lost
+    #[error("{0} scheduled notes would be lost, publish or cancel them first")]
+    ScheduledPending(usize),
+    /// Note scheduled for a time that has passed
+    #[error("Scheduled time {0} is not in the future")]
+    ScheduledInPast(u64),
+    /// Neither a pubkey nor a NIP05 identifier
+    #[error("Invalid pubkey or NIP05 identifier {0}")]
+    InvalidPubkey(String),
+    /// NIP05 lookup failure
+    #[error("NIP05 error: {0}")]
+    Nip05(#[from] Nip05Error),
+    /// NIP49 key encryption failure
+    #[error("Key encryption error: {0}")]
+    Nip49(#[from] Nip49Error),
+    /// Stored or imported data has an unknown schema version
+    #[error("Unsupported version {0}")]
+    UnsupportedVersion(u32),
+    /// Browser storage failure
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    /// IndexedDB failure
+    #[error("IndexedDB error: {0}")]
+    IndexedDb(#[from] rexie::Error),
+    /// Storage backend name not recognised
+    #[error("Unknown storage backend {0}")]
+    UnknownBackend(String),
+    /// (De)serialization failure
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Reason a delegation does not cover an event
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DelegationError {
+    /// Event kind is not in the delegation conditions
+    #[error("kind {0} is not allowed")]
+    KindNotAllowed(u64),
+    /// Event is created before the delegation is valid
+    #[error("not valid until {0}")]
+    NotYetValid(u64),
+    /// Event is created after the delegation expired
+    #[error("expired at {0}")]
+    Expired(u64),
+}
+
+fn join_reasons(reasons: &[DelegationError]) -> String {
+    reasons
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl NostrServiceError {
+    /// Map a client error from a NIP46 request to a signer error
+    pub fn from_signer(err: ClientError) -> Self {
+        match err {
+            ClientError::Timeout => Self::SignerTimeout,
+            err => Self::SignerRejected(err.to_string()),
+        }
+    }
+}
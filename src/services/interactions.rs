@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+
+use nostr_sdk::prelude::*;
+
+/// NIP18 generic repost of an event that is not a text note
+pub const GENERIC_REPOST: u64 = 16;
+
+/// Reaction kinds and reposts counted for events
+pub fn interaction_kinds() -> Vec<Kind> {
+    vec![Kind::Reaction, Kind::Repost, Kind::from(GENERIC_REPOST)]
+}
+
+/// NIP25 reaction content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reaction {
+    Like,
+    Dislike,
+    /// Unicode emoji or any other text
+    Emoji(String),
+    /// NIP30 custom emoji shown as an image
+    CustomEmoji {
+        shortcode: String,
+        url: Url,
+    },
+}
+
+impl Reaction {
+    /// Parse reaction input, `url` makes a `:shortcode:` a custom emoji
+    pub fn parse(content: &str, url: Option<&str>) -> Option<Self> {
+        let content = content.trim();
+        let shortcode = content
+            .strip_prefix(':')
+            .and_then(|content| content.strip_suffix(':'))
+            .filter(|shortcode| {
+                !shortcode.is_empty()
+                    && shortcode
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            });
+        let url = url
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .and_then(|url| Url::parse(url).ok());
+
+        match (content, shortcode, url) {
+            ("", ..) => None,
+            ("+", ..) => Some(Self::Like),
+            ("-", ..) => Some(Self::Dislike),
+            (_, Some(shortcode), Some(url)) => Some(Self::CustomEmoji {
+                shortcode: shortcode.to_string(),
+                url,
+            }),
+            (content, ..) => Some(Self::Emoji(content.to_string())),
+        }
+    }
+
+    pub fn content(&self) -> String {
+        match self {
+            Self::Like => "+".to_string(),
+            Self::Dislike => "-".to_string(),
+            Self::Emoji(emoji) => emoji.clone(),
+            Self::CustomEmoji { shortcode, .. } => format!(":{}:", shortcode),
+        }
+    }
+
+    /// `e` and `p` tags of the reaction to `target`, with the `emoji` tag of a custom emoji
+    pub fn tags(&self, target: &Event) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::Event(target.id, None, None),
+            Tag::PubKey(target.pubkey, None),
+        ];
+        if let Self::CustomEmoji { shortcode, url } = self {
+            tags.push(Tag::Generic(
+                TagKind::Custom("emoji".to_string()),
+                vec![shortcode.clone(), url.to_string()],
+            ));
+        }
+        tags
+    }
+}
+
+/// Kind and tags of a repost of `target`, kind 6 for text notes and kind 16 otherwise
+pub fn repost_tags(target: &Event) -> (Kind, Vec<Tag>) {
+    let mut tags = vec![
+        Tag::Event(target.id, None, None),
+        Tag::PubKey(target.pubkey, None),
+    ];
+    if target.kind == Kind::TextNote {
+        (Kind::Repost, tags)
+    } else {
+        tags.push(Tag::Generic(
+            TagKind::Custom("k".to_string()),
+            vec![target.kind.as_u64().to_string()],
+        ));
+        (Kind::from(GENERIC_REPOST), tags)
+    }
+}
+
+/// Event a reaction or repost is for, the last `e` tag
+fn target_id(event: &Event) -> Option<EventId> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            Tag::Event(id, ..) => Some(*id),
+            _ => None,
+        })
+        .last()
+}
+
+/// Reactions and reposts of one event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InteractionCounts {
+    /// How many times each reaction content was sent, `+` for likes
+    pub reactions: HashMap<String, usize>,
+    pub reposts: usize,
+}
+
+/// Reaction and repost counts by event, each reaction or repost counted once
+#[derive(Debug, Clone, Default)]
+pub struct Interactions {
+    seen: HashSet<EventId>,
+    counts: HashMap<EventId, InteractionCounts>,
+}
+
+impl Interactions {
+    /// Count the reactions and reposts not seen yet
+    pub fn insert(&mut self, events: Vec<Event>) {
+        for event in events {
+            let target = match target_id(&event) {
+                Some(target) => target,
+                None => continue,
+            };
+            if !self.seen.insert(event.id) {
+                continue;
+            }
+
+            let counts = self.counts.entry(target).or_default();
+            if event.kind == Kind::Reaction {
+                // Empty content is a like
+                let content = match event.content.trim() {
+                    "" => "+".to_string(),
+                    content => content.to_string(),
+                };
+                *counts.reactions.entry(content).or_default() += 1;
+            } else {
+                counts.reposts += 1;
+            }
+        }
+    }
+
+    pub fn get(&self, id: &EventId) -> Option<&InteractionCounts> {
+        self.counts.get(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note() -> Event {
+        EventBuilder::new_text_note("note", &[])
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    fn reaction(target: &Event, content: &str) -> Event {
+        EventBuilder::new(Kind::Reaction, content, &Reaction::Like.tags(target))
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    fn repost(target: &Event) -> Event {
+        let (kind, tags) = repost_tags(target);
+        EventBuilder::new(kind, "", &tags)
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_likes_and_dislikes() {
+        assert_eq!(Reaction::parse(" + ", None), Some(Reaction::Like));
+        assert_eq!(Reaction::parse("-", None), Some(Reaction::Dislike));
+        assert_eq!(Reaction::parse("  ", None), None);
+    }
+
+    #[test]
+    fn parses_emoji() {
+        assert_eq!(
+            Reaction::parse("🤙", None),
+            Some(Reaction::Emoji("🤙".to_string()))
+        );
+        // A shortcode without an image is sent as text
+        assert_eq!(
+            Reaction::parse(":soapbox:", Some(" ")),
+            Some(Reaction::Emoji(":soapbox:".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_custom_emoji() {
+        let url = "https://example.com/soapbox.png";
+        let reaction = Reaction::parse(":soapbox:", Some(url)).unwrap();
+
+        assert_eq!(
+            reaction,
+            Reaction::CustomEmoji {
+                shortcode: "soapbox".to_string(),
+                url: Url::parse(url).unwrap(),
+            }
+        );
+        assert_eq!(reaction.content(), ":soapbox:");
+        assert_eq!(reaction.tags(&note()).len(), 3);
+
+        // Not a valid shortcode or URL
+        assert_eq!(
+            Reaction::parse(":soap box:", Some(url)),
+            Some(Reaction::Emoji(":soap box:".to_string()))
+        );
+        assert_eq!(
+            Reaction::parse(":soapbox:", Some("not a url")),
+            Some(Reaction::Emoji(":soapbox:".to_string()))
+        );
+    }
+
+    #[test]
+    fn counts_reactions_and_reposts() {
+        let target = note();
+        let mut interactions = Interactions::default();
+        interactions.insert(vec![
+            reaction(&target, "+"),
+            reaction(&target, ""),
+            reaction(&target, "🤙"),
+            repost(&target),
+        ]);
+
+        let counts = interactions.get(&target.id).unwrap();
+        assert_eq!(counts.reactions.get("+"), Some(&2));
+        assert_eq!(counts.reactions.get("🤙"), Some(&1));
+        assert_eq!(counts.reposts, 1);
+    }
+
+    #[test]
+    fn counts_each_event_once() {
+        let target = note();
+        let like = reaction(&target, "+");
+        let mut interactions = Interactions::default();
+        interactions.insert(vec![like.clone(), like.clone()]);
+        interactions.insert(vec![like]);
+
+        assert_eq!(
+            interactions.get(&target.id).unwrap().reactions.get("+"),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn ignores_events_without_target() {
+        let untargeted = EventBuilder::new(Kind::Reaction, "+", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        let mut interactions = Interactions::default();
+        interactions.insert(vec![untargeted.clone()]);
+
+        assert_eq!(interactions.get(&untargeted.id), None);
+        assert!(interactions.seen.is_empty());
+    }
+
+    #[test]
+    fn reposts_other_kinds_as_generic() {
+        let metadata = EventBuilder::new(Kind::Metadata, "{}", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+
+        assert_eq!(repost_tags(&note()).0, Kind::Repost);
+        let (kind, tags) = repost_tags(&metadata);
+        assert_eq!(kind, Kind::from(GENERIC_REPOST));
+        assert_eq!(tags.len(), 3);
+    }
+}
@@ -1 +1,10 @@
+pub mod bundle;
+pub mod error;
+pub mod interactions;
+pub mod nip05;
+pub mod nip10;
+pub mod nip49;
 pub mod nostr;
+pub mod outbox;
+pub mod storage;
+pub mod store;
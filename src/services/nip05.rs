@@ -0,0 +1,225 @@
+use std::{collections::HashMap, str::FromStr};
+
+use futures::future::LocalBoxFuture;
+use gloo::net::http::Request;
+use nostr_sdk::secp256k1::XOnlyPublicKey;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Nip05Error {
+    /// Not a `name@domain` identifier
+    #[error("Invalid NIP05 identifier {0}")]
+    InvalidIdentifier(String),
+    /// Request to the well known endpoint failed
+    #[error("Could not fetch {0}")]
+    Http(String),
+    /// Endpoint did not return a nostr.json document
+    #[error("Invalid nostr.json: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Name is not listed by the domain
+    #[error("{0} is not listed by its domain")]
+    NotFound(String),
+    /// Listed pubkey is not valid hex
+    #[error("Invalid pubkey listed for {0}")]
+    InvalidPubkey(String),
+}
+
+/// HTTP GET returning the response body, injectable so lookups can be stubbed
+pub trait HttpFetcher {
+    fn get(&self, url: String) -> LocalBoxFuture<'static, Result<String, Nip05Error>>;
+}
+
+/// Fetcher using the browser fetch API
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BrowserFetcher;
+
+impl HttpFetcher for BrowserFetcher {
+    fn get(&self, url: String) -> LocalBoxFuture<'static, Result<String, Nip05Error>> {
+        Box::pin(async move {
+            let response = Request::get(&url)
+                .send()
+                .await
+                .map_err(|_| Nip05Error::Http(url.clone()))?;
+            if !response.ok() {
+                return Err(Nip05Error::Http(url));
+            }
+            response.text().await.map_err(|_| Nip05Error::Http(url))
+        })
+    }
+}
+
+/// `/.well-known/nostr.json` document
+#[derive(Debug, Deserialize)]
+struct NostrJson {
+    names: HashMap<String, String>,
+}
+
+/// Whether `input` looks like a NIP05 identifier rather than a key
+pub fn is_identifier(input: &str) -> bool {
+    input.trim().contains('.')
+}
+
+/// Split an identifier into name and domain, a bare domain is `_@domain`
+fn parse_identifier(identifier: &str) -> Result<(String, String), Nip05Error> {
+    let identifier = identifier.trim().to_lowercase();
+    let (name, domain) = match identifier.split_once('@') {
+        Some((name, domain)) => (name.to_string(), domain.to_string()),
+        None => ("_".to_string(), identifier.clone()),
+    };
+
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_name || !domain.contains('.') || domain.contains('/') {
+        return Err(Nip05Error::InvalidIdentifier(identifier));
+    }
+
+    Ok((name, domain))
+}
+
+/// Resolve a NIP05 identifier to the pubkey listed by its domain
+pub async fn resolve(
+    fetcher: &dyn HttpFetcher,
+    identifier: &str,
+) -> Result<XOnlyPublicKey, Nip05Error> {
+    let (name, domain) = parse_identifier(identifier)?;
+    let url = format!("https://{}/.well-known/nostr.json?name={}", domain, name);
+
+    let body = fetcher.get(url).await?;
+    let nostr_json: NostrJson = serde_json::from_str(&body)?;
+    let pubkey = nostr_json
+        .names
+        .get(&name)
+        .ok_or_else(|| Nip05Error::NotFound(identifier.to_string()))?;
+
+    XOnlyPublicKey::from_str(pubkey).map_err(|_| Nip05Error::InvalidPubkey(identifier.to_string()))
+}
+
+/// Check that the domain of `identifier` lists `pubkey`
+pub async fn verify(
+    fetcher: &dyn HttpFetcher,
+    identifier: &str,
+    pubkey: &XOnlyPublicKey,
+) -> Result<bool, Nip05Error> {
+    Ok(&resolve(fetcher, identifier).await? == pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    const PUBKEY: &str = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+    const OTHER_PUBKEY: &str = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
+
+    /// Serves nostr.json documents by URL
+    struct StubFetcher(HashMap<String, String>);
+
+    impl StubFetcher {
+        fn new(url: &str, body: &str) -> Self {
+            Self(HashMap::from([(url.to_string(), body.to_string())]))
+        }
+    }
+
+    impl HttpFetcher for StubFetcher {
+        fn get(&self, url: String) -> LocalBoxFuture<'static, Result<String, Nip05Error>> {
+            let body = self.0.get(&url).cloned().ok_or(Nip05Error::Http(url));
+            Box::pin(async move { body })
+        }
+    }
+
+    fn names(name: &str, pubkey: &str) -> String {
+        format!(r#"{{"names":{{"{}":"{}"}}}}"#, name, pubkey)
+    }
+
+    #[test]
+    fn resolves_listed_name() {
+        let fetcher = StubFetcher::new(
+            "https://example.com/.well-known/nostr.json?name=bob",
+            &names("bob", PUBKEY),
+        );
+
+        let pubkey = block_on(resolve(&fetcher, "Bob@Example.com")).unwrap();
+
+        assert_eq!(pubkey, XOnlyPublicKey::from_str(PUBKEY).unwrap());
+    }
+
+    #[test]
+    fn resolves_bare_domain_as_underscore() {
+        let fetcher = StubFetcher::new(
+            "https://example.com/.well-known/nostr.json?name=_",
+            &names("_", PUBKEY),
+        );
+
+        let pubkey = block_on(resolve(&fetcher, "example.com")).unwrap();
+
+        assert_eq!(pubkey, XOnlyPublicKey::from_str(PUBKEY).unwrap());
+    }
+
+    #[test]
+    fn rejects_unlisted_name() {
+        let fetcher = StubFetcher::new(
+            "https://example.com/.well-known/nostr.json?name=alice",
+            &names("bob", PUBKEY),
+        );
+
+        assert!(matches!(
+            block_on(resolve(&fetcher, "alice@example.com")),
+            Err(Nip05Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        let fetcher = StubFetcher::new(
+            "https://example.com/.well-known/nostr.json?name=bob",
+            &names("bob", "not-hex"),
+        );
+
+        assert!(matches!(
+            block_on(resolve(&fetcher, "bob@example.com")),
+            Err(Nip05Error::InvalidPubkey(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_identifier() {
+        let fetcher = StubFetcher(HashMap::new());
+
+        assert!(matches!(
+            block_on(resolve(&fetcher, "bob@localhost")),
+            Err(Nip05Error::InvalidIdentifier(_))
+        ));
+        assert!(matches!(
+            block_on(resolve(&fetcher, "b b@example.com")),
+            Err(Nip05Error::InvalidIdentifier(_))
+        ));
+    }
+
+    #[test]
+    fn verifies_matching_pubkey() {
+        let fetcher = StubFetcher::new(
+            "https://example.com/.well-known/nostr.json?name=bob",
+            &names("bob", PUBKEY),
+        );
+        let pubkey = XOnlyPublicKey::from_str(PUBKEY).unwrap();
+        let other = XOnlyPublicKey::from_str(OTHER_PUBKEY).unwrap();
+
+        assert!(block_on(verify(&fetcher, "bob@example.com", &pubkey)).unwrap());
+        assert!(!block_on(verify(&fetcher, "bob@example.com", &other)).unwrap());
+    }
+
+    #[test]
+    fn fails_verification_when_fetch_fails() {
+        let fetcher = StubFetcher(HashMap::new());
+        let pubkey = XOnlyPublicKey::from_str(PUBKEY).unwrap();
+
+        assert!(matches!(
+            block_on(verify(&fetcher, "bob@example.com", &pubkey)),
+            Err(Nip05Error::Http(_))
+        ));
+    }
+}
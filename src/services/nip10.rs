@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet};
+
+use nostr_sdk::prelude::*;
+
+/// Marker of `e` tags referencing a quoted event
+const MENTION_MARKER: &str = "mention";
+
+/// `e` tags of `event` with their marker
+fn event_tags(event: &Event) -> Vec<(EventId, Option<&Marker>)> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            Tag::Event(id, _, marker) => Some((*id, marker.as_ref())),
+            _ => None,
+        })
+        .filter(|(_, marker)| !matches!(marker, Some(Marker::Custom(m)) if m == MENTION_MARKER))
+        .collect()
+}
+
+/// Whether `event` tags its references with markers rather than by position
+fn uses_markers(tags: &[(EventId, Option<&Marker>)]) -> bool {
+    tags.iter().any(|(_, marker)| marker.is_some())
+}
+
+/// Root of the thread `event` replies to, `None` if `event` is a root
+pub fn root_id(event: &Event) -> Option<EventId> {
+    let tags = event_tags(event);
+    if uses_markers(&tags) {
+        tags.iter()
+            .find(|(_, marker)| matches!(marker, Some(Marker::Root)))
+            .or_else(|| {
+                tags.iter()
+                    .find(|(_, marker)| matches!(marker, Some(Marker::Reply)))
+            })
+            .map(|(id, _)| *id)
+    } else {
+        // Deprecated positional tags, the first is the root
+        tags.first().map(|(id, _)| *id)
+    }
+}
+
+/// Event `event` directly replies to, `None` if `event` is a root
+pub fn parent_id(event: &Event) -> Option<EventId> {
+    let tags = event_tags(event);
+    if uses_markers(&tags) {
+        tags.iter()
+            .find(|(_, marker)| matches!(marker, Some(Marker::Reply)))
+            .map(|(id, _)| *id)
+            .or_else(|| root_id(event))
+    } else {
+        // Deprecated positional tags, the last is the parent
+        tags.last().map(|(id, _)| *id)
+    }
+}
+
+/// `p` tags for everyone in a conversation: the author of `event` and the pubkeys it tags
+fn pubkey_tags(event: &Event) -> Vec<Tag> {
+    let mut seen = HashSet::new();
+    std::iter::once(event.pubkey)
+        .chain(event.tags.iter().filter_map(|tag| match tag {
+            Tag::PubKey(pubkey, _) => Some(*pubkey),
+            _ => None,
+        }))
+        .filter(|pubkey| seen.insert(*pubkey))
+        .map(|pubkey| Tag::PubKey(pubkey, None))
+        .collect()
+}
+
+/// Marked `e` tags and `p` tags of a reply to `parent`
+pub fn reply_tags(parent: &Event) -> Vec<Tag> {
+    let mut tags = match root_id(parent) {
+        Some(root) => vec![
+            Tag::Event(root, None, Some(Marker::Root)),
+            Tag::Event(parent.id, None, Some(Marker::Reply)),
+        ],
+        None => vec![Tag::Event(parent.id, None, Some(Marker::Root))],
+    };
+    tags.extend(pubkey_tags(parent));
+    tags
+}
+
+/// `e` mention tag and `p` tag of a note quoting `quoted`
+pub fn quote_tags(quoted: &Event) -> Vec<Tag> {
+    vec![
+        Tag::Event(
+            quoted.id,
+            None,
+            Some(Marker::Custom(MENTION_MARKER.to_string())),
+        ),
+        Tag::PubKey(quoted.pubkey, None),
+    ]
+}
+
+/// Order the events of the thread of `root` depth first, with their depth in the reply tree
+///
+/// Replies are sorted oldest first, replies to events that were not found are
+/// attached to the root
+pub fn thread(events: Vec<Event>, root: EventId) -> Vec<(usize, Event)> {
+    let ids: HashSet<EventId> = events.iter().map(|event| event.id).collect();
+    let mut root_event = None;
+    let mut children: HashMap<EventId, Vec<Event>> = HashMap::new();
+    for event in events {
+        if event.id == root {
+            root_event = Some(event);
+            continue;
+        }
+        let parent = parent_id(&event)
+            .filter(|parent| ids.contains(parent))
+            .unwrap_or(root);
+        children.entry(parent).or_default().push(event);
+    }
+    // Newest first, so the oldest reply is popped first
+    for replies in children.values_mut() {
+        replies.sort_by_key(|event| std::cmp::Reverse(event.created_at));
+    }
+
+    let mut stack: Vec<(usize, Event)> = match root_event {
+        Some(event) => vec![(0, event)],
+        // Root was not found, show its replies at the top level
+        None => children
+            .remove(&root)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|event| (0, event))
+            .collect(),
+    };
+
+    let mut thread = Vec::with_capacity(ids.len());
+    while let Some((depth, event)) = stack.pop() {
+        if let Some(replies) = children.remove(&event.id) {
+            stack.extend(replies.into_iter().map(|reply| (depth + 1, reply)));
+        }
+        thread.push((depth, event));
+    }
+    thread
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(tags: &[Tag], created_at: u64) -> Event {
+        let mut event = EventBuilder::new(Kind::TextNote, "note", tags)
+            .to_event(&Keys::generate())
+            .unwrap();
+        event.created_at = Timestamp::from(created_at);
+        event
+    }
+
+    fn positional(ids: &[EventId]) -> Vec<Tag> {
+        ids.iter().map(|id| Tag::Event(*id, None, None)).collect()
+    }
+
+    fn thread_ids(thread: &[(usize, Event)]) -> Vec<(usize, EventId)> {
+        thread
+            .iter()
+            .map(|(depth, event)| (*depth, event.id))
+            .collect()
+    }
+
+    #[test]
+    fn root_has_no_references() {
+        let root = note(&[], 1);
+
+        assert_eq!(root_id(&root), None);
+        assert_eq!(parent_id(&root), None);
+    }
+
+    #[test]
+    fn reads_marked_tags() {
+        let root = note(&[], 1);
+        let parent = note(&reply_tags(&root), 2);
+        let reply = note(&reply_tags(&parent), 3);
+
+        assert_eq!(root_id(&parent), Some(root.id));
+        assert_eq!(parent_id(&parent), Some(root.id));
+        assert_eq!(root_id(&reply), Some(root.id));
+        assert_eq!(parent_id(&reply), Some(parent.id));
+    }
+
+    #[test]
+    fn reads_positional_tags() {
+        let root = note(&[], 1);
+        let parent = note(&positional(&[root.id]), 2);
+        let reply = note(&positional(&[root.id, parent.id]), 3);
+
+        assert_eq!(root_id(&parent), Some(root.id));
+        assert_eq!(parent_id(&parent), Some(root.id));
+        assert_eq!(root_id(&reply), Some(root.id));
+        assert_eq!(parent_id(&reply), Some(parent.id));
+    }
+
+    #[test]
+    fn falls_back_to_reply_without_root_marker() {
+        let parent = note(&[], 1);
+        let reply = note(&[Tag::Event(parent.id, None, Some(Marker::Reply))], 2);
+
+        assert_eq!(root_id(&reply), Some(parent.id));
+        assert_eq!(parent_id(&reply), Some(parent.id));
+    }
+
+    #[test]
+    fn ignores_mentions() {
+        let quoted = note(&[], 1);
+        let quote = note(&quote_tags(&quoted), 2);
+
+        assert_eq!(root_id(&quote), None);
+        assert_eq!(parent_id(&quote), None);
+    }
+
+    #[test]
+    fn reply_tags_mark_root_and_parent() {
+        let root = note(&[], 1);
+        let parent = note(&reply_tags(&root), 2);
+        let tags = reply_tags(&parent);
+
+        assert_eq!(tags[0], Tag::Event(root.id, None, Some(Marker::Root)));
+        assert_eq!(tags[1], Tag::Event(parent.id, None, Some(Marker::Reply)));
+        // Author of the parent, and the root author it tags, once each
+        let pubkeys: Vec<&Tag> = tags
+            .iter()
+            .filter(|tag| matches!(tag, Tag::PubKey(..)))
+            .collect();
+        assert_eq!(
+            pubkeys,
+            vec![
+                &Tag::PubKey(parent.pubkey, None),
+                &Tag::PubKey(root.pubkey, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_thread_depth_first() {
+        let root = note(&[], 1);
+        let first = note(&reply_tags(&root), 2);
+        let second = note(&reply_tags(&root), 3);
+        let nested = note(&reply_tags(&first), 4);
+
+        let thread = thread(
+            vec![nested.clone(), second.clone(), root.clone(), first.clone()],
+            root.id,
+        );
+
+        assert_eq!(
+            thread_ids(&thread),
+            vec![(0, root.id), (1, first.id), (2, nested.id), (1, second.id)]
+        );
+    }
+
+    #[test]
+    fn attaches_orphans_to_root() {
+        let root = note(&[], 1);
+        let missing = note(&reply_tags(&root), 2);
+        let orphan = note(&reply_tags(&missing), 3);
+
+        let thread = thread(vec![orphan.clone(), root.clone()], root.id);
+
+        assert_eq!(thread_ids(&thread), vec![(0, root.id), (1, orphan.id)]);
+    }
+
+    #[test]
+    fn shows_replies_when_root_is_missing() {
+        let root = note(&[], 1);
+        let first = note(&reply_tags(&root), 2);
+        let second = note(&reply_tags(&root), 3);
+
+        let thread = thread(vec![second.clone(), first.clone()], root.id);
+
+        assert_eq!(thread_ids(&thread), vec![(0, first.id), (0, second.id)]);
+    }
+}
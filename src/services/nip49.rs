@@ -0,0 +1,208 @@
+use bech32::{FromBase32, ToBase32, Variant};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use nostr_sdk::secp256k1::SecretKey;
+use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+/// Bech32 human readable part
+const HRP: &str = "ncryptsec";
+
+/// Version of the ncryptsec payload
+const VERSION: u8 = 0x02;
+
+/// Key security byte, whether the key was known to be handled insecurely is not tracked
+const KEY_SECURITY_UNKNOWN: u8 = 0x02;
+
+/// version + log_n + salt + nonce + key security + ciphertext
+const PAYLOAD_LEN: usize = 1 + 1 + 16 + 24 + 1 + 48;
+
+/// scrypt cost parameter recommended by NIP49
+pub const DEFAULT_LOG_N: u8 = 16;
+
+/// Highest scrypt cost accepted when decrypting, 2^20 already needs 1 GiB of memory
+pub const MAX_LOG_N: u8 = 20;
+
+#[derive(Debug, Error)]
+pub enum Nip49Error {
+    /// Bech32 encoding error
+    #[error("Bech32 error: {0}")]
+    Bech32(#[from] bech32::Error),
+    /// Not an ncryptsec string
+    #[error("Expected ncryptsec, got {0}")]
+    Hrp(String),
+    /// Unknown payload version
+    #[error("Unsupported ncryptsec version {0}")]
+    Version(u8),
+    /// Payload has the wrong length
+    #[error("Invalid ncryptsec length")]
+    Length,
+    /// Wrong password or tampered payload
+    #[error("Wrong password or corrupted key")]
+    Decrypt,
+    /// Encryption failed
+    #[error("Could not encrypt key")]
+    Encrypt,
+    /// Invalid scrypt parameters
+    #[error("Key derivation failed")]
+    Scrypt,
+    /// scrypt cost too high to derive the key in the browser
+    #[error("Key derivation cost 2^{0} is too high")]
+    LogN(u8),
+    /// Browser random source unavailable
+    #[error("Random number generation failed")]
+    Random,
+    /// Decrypted bytes are not a valid secret key
+    #[error(transparent)]
+    Secp256k1(#[from] nostr_sdk::secp256k1::Error),
+}
+
+/// Encrypt a secret key with `password` as NIP49 `ncryptsec`
+pub fn encrypt(secret_key: &SecretKey, password: &str, log_n: u8) -> Result<String, Nip49Error> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 24];
+    getrandom::getrandom(&mut salt).map_err(|_| Nip49Error::Random)?;
+    getrandom::getrandom(&mut nonce).map_err(|_| Nip49Error::Random)?;
+
+    let key = derive_key(password, &salt, log_n)?;
+    let secret = Zeroizing::new(secret_key.secret_bytes());
+    let cipher = XChaCha20Poly1305::new((&*key).into());
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: secret.as_slice(),
+                aad: &[KEY_SECURITY_UNKNOWN],
+            },
+        )
+        .map_err(|_| Nip49Error::Encrypt)?;
+
+    let mut payload = Vec::with_capacity(PAYLOAD_LEN);
+    payload.push(VERSION);
+    payload.push(log_n);
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.push(KEY_SECURITY_UNKNOWN);
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(bech32::encode(HRP, payload.to_base32(), Variant::Bech32)?)
+}
+
+/// Decrypt an `ncryptsec` with `password`
+pub fn decrypt(ncryptsec: &str, password: &str) -> Result<SecretKey, Nip49Error> {
+    let (hrp, data, _) = bech32::decode(ncryptsec.trim())?;
+    if hrp != HRP {
+        return Err(Nip49Error::Hrp(hrp));
+    }
+
+    let payload = Vec::<u8>::from_base32(&data)?;
+    if payload.len() != PAYLOAD_LEN {
+        return Err(Nip49Error::Length);
+    }
+    if payload[0] != VERSION {
+        return Err(Nip49Error::Version(payload[0]));
+    }
+
+    // The payload may come from an untrusted bundle
+    let log_n = payload[1];
+    if log_n > MAX_LOG_N {
+        return Err(Nip49Error::LogN(log_n));
+    }
+    let salt = &payload[2..18];
+    let nonce = &payload[18..42];
+    let key_security = &payload[42..43];
+    let ciphertext = &payload[43..];
+
+    let key = derive_key(password, salt, log_n)?;
+    let cipher = XChaCha20Poly1305::new((&*key).into());
+    let secret = cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: key_security,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| Nip49Error::Decrypt)?;
+
+    Ok(SecretKey::from_slice(&secret)?)
+}
+
+/// scrypt key from the NFKC normalized password, wiped when dropped
+fn derive_key(password: &str, salt: &[u8], log_n: u8) -> Result<Zeroizing<[u8; 32]>, Nip49Error> {
+    let password: Zeroizing<String> = Zeroizing::new(password.nfkc().collect());
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|_| Nip49Error::Scrypt)?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(password.as_bytes(), salt, &params, key.as_mut())
+        .map_err(|_| Nip49Error::Scrypt)?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// Test vector of the NIP49 specification, encrypted with log_n 16
+    const SPEC_NCRYPTSEC: &str = "ncryptsec1qgg9947rlpvqu76pj5ecreduf9jxhselq2nae2kghhvd5g7dgjtcxfqtd67p9m0w57lspw8gsq6yphnm8623nsl8xn9j4jdzz84zm3frztj3z7s35vpzmqf6ksu8r89qk5z2zxfmu5gv8th8wclt0h4p";
+    const SPEC_SECRET_KEY: &str =
+        "3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683";
+
+    /// Low cost so the round trips stay fast
+    const TEST_LOG_N: u8 = 4;
+
+    #[test]
+    fn decrypts_spec_vector() {
+        let secret_key = decrypt(SPEC_NCRYPTSEC, "nostr").unwrap();
+        assert_eq!(secret_key, SecretKey::from_str(SPEC_SECRET_KEY).unwrap());
+    }
+
+    #[test]
+    fn round_trips() {
+        let secret_key = SecretKey::from_str(SPEC_SECRET_KEY).unwrap();
+        let ncryptsec = encrypt(&secret_key, "correct horse", TEST_LOG_N).unwrap();
+
+        assert!(ncryptsec.starts_with("ncryptsec1"));
+        assert_eq!(decrypt(&ncryptsec, "correct horse").unwrap(), secret_key);
+    }
+
+    #[test]
+    fn normalizes_password() {
+        let secret_key = SecretKey::from_str(SPEC_SECRET_KEY).unwrap();
+        // Precomposed and decomposed forms of the same text
+        let ncryptsec = encrypt(&secret_key, "\u{e9}", TEST_LOG_N).unwrap();
+
+        assert_eq!(decrypt(&ncryptsec, "e\u{301}").unwrap(), secret_key);
+    }
+
+    #[test]
+    fn rejects_excessive_cost() {
+        let (_, data, _) = bech32::decode(SPEC_NCRYPTSEC).unwrap();
+        let mut payload = Vec::<u8>::from_base32(&data).unwrap();
+        payload[1] = MAX_LOG_N + 1;
+        let ncryptsec = bech32::encode(HRP, payload.to_base32(), Variant::Bech32).unwrap();
+
+        assert!(matches!(
+            decrypt(&ncryptsec, "nostr"),
+            Err(Nip49Error::LogN(log_n)) if log_n == MAX_LOG_N + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_password() {
+        let secret_key = SecretKey::from_str(SPEC_SECRET_KEY).unwrap();
+        let ncryptsec = encrypt(&secret_key, "correct horse", TEST_LOG_N).unwrap();
+
+        assert!(matches!(
+            decrypt(&ncryptsec, "wrong horse"),
+            Err(Nip49Error::Decrypt)
+        ));
+    }
+}
@@ -1,9 +1,17 @@
-use std::{collections::HashSet, str::FromStr, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+    rc::Rc,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
-use anyhow::Result;
 use dashmap::DashSet;
-use gloo::storage::{SessionStorage, Storage};
-use log::{debug, error, warn};
+use futures::{future::join_all, Stream};
+use gloo::timers::future::IntervalStream;
+use log::{debug, warn};
 use nostr_sdk::{
     prelude::*,
     secp256k1::{schnorr::Signature, XOnlyPublicKey},
@@ -12,7 +20,55 @@ use nostr_sdk::{
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use wasm_bindgen_futures::spawn_local;
-use yew::{AttrValue, Callback};
+
+use super::bundle::SessionBundle;
+use super::error::{DelegationError, NostrServiceError};
+use super::interactions::{self, Reaction};
+use super::nip05::{self, BrowserFetcher, HttpFetcher};
+use super::nip10;
+use super::outbox::OutboxItem;
+use super::storage::{Account, RelayConfig};
+use super::store::Store;
+use crate::utils::parse_pubkey;
+
+/// How long to wait for the remote signer to answer a request
+const SIGNER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Delegation length used when renewing a delegation without a window
+const DEFAULT_DELEGATION_LENGTH: u64 = 24 * 60 * 60;
+
+/// How often held delegations are checked for expiry, in milliseconds
+const EXPIRY_CHECK_INTERVAL: u32 = 30_000;
+
+/// How long before expiry to warn about a delegation, in seconds
+const EXPIRY_WARNING: u64 = 10 * 60;
+
+/// How long to wait for relays when fetching events
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for each relay to acknowledge a published event
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Published events kept in the history of an account
+const HISTORY_LIMIT: usize = 500;
+
+/// How often the outbox is checked for events due a resend, in milliseconds
+const OUTBOX_CHECK_INTERVAL: u32 = 5_000;
+
+/// How often scheduled notes are checked for their publish time, in milliseconds
+const SCHEDULE_CHECK_INTERVAL: u32 = 1_000;
+
+/// Direct messages requested per direction
+const DM_PAGE_SIZE: usize = 100;
+
+/// Direct messages decrypted per request, each one is a round trip to the remote signer
+const DECRYPT_BATCH: usize = 20;
+
+/// Notes requested per timeline page
+const TIMELINE_PAGE_SIZE: usize = 50;
+
+/// How often the timeline is polled for new notes, in milliseconds
+const TIMELINE_POLL_INTERVAL: u32 = 15_000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DelegationInfo {
@@ -55,6 +111,297 @@ impl DelegationInfo {
             })
             .collect()
     }
+
+    /// Check the delegation was signed for `app_pubkey`
+    pub fn verify_for(&self, app_pubkey: XOnlyPublicKey) -> Result<(), NostrServiceError> {
+        verify_delegation_signature(
+            self.delegator_pubkey,
+            self.signature,
+            app_pubkey,
+            self.conditions.clone(),
+        )
+        .map_err(|err| NostrServiceError::DelegationInvalid(err.to_string()))
+    }
+
+    /// Check the conditions allow an event of `kind` created at `created_at`
+    pub fn validate_for(&self, kind: Kind, created_at: Timestamp) -> Result<(), DelegationError> {
+        let kinds = self.kinds();
+        let created_at = created_at.as_u64();
+
+        if !kinds.is_empty() && !kinds.contains(&kind.as_u64()) {
+            return Err(DelegationError::KindNotAllowed(kind.as_u64()));
+        }
+
+        if let Some(after) = self.created_after() {
+            if created_at <= after {
+                return Err(DelegationError::NotYetValid(after));
+            }
+        }
+
+        if let Some(before) = self.created_before() {
+            if created_at >= before {
+                return Err(DelegationError::Expired(before));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Conditions for a new delegation like this one valid from now, also covering `kind` if set
+    pub fn renewal_conditions(&self, kind: Option<Kind>) -> Conditions {
+        let now = Timestamp::now().as_u64();
+        let valid_for = match (self.created_after(), self.created_before()) {
+            (Some(after), Some(before)) if before > after => before - after,
+            _ => DEFAULT_DELEGATION_LENGTH,
+        };
+
+        let mut kinds = self.kinds();
+        if let Some(kind) = kind {
+            if !kinds.is_empty() && !kinds.contains(&kind.as_u64()) {
+                kinds.push(kind.as_u64());
+            }
+        }
+
+        let mut conditions = Conditions::new();
+        for kind in kinds {
+            conditions.add(Condition::Kind(kind));
+        }
+        conditions.add(Condition::CreatedAfter(now));
+        conditions.add(Condition::CreatedBefore(now + valid_for));
+
+        conditions
+    }
+
+    /// Parse a NIP26 delegation tag `["delegation", <pubkey>, <conditions>, <sig>]`
+    pub fn from_tag_json(tag: &str) -> Result<Self, NostrServiceError> {
+        let tag: Vec<String> = serde_json::from_str(tag.trim())?;
+
+        match tag.as_slice() {
+            [name, delegator, conditions, signature] if name == "delegation" => {
+                Self::from_parts(delegator, conditions, signature)
+            }
+            _ => Err(NostrServiceError::DelegationInvalid(
+                "expected [\"delegation\", pubkey, conditions, sig]".to_string(),
+            )),
+        }
+    }
+
+    /// Build from delegator pubkey (hex or npub), conditions query string and hex signature
+    pub fn from_parts(
+        delegator: &str,
+        conditions: &str,
+        signature: &str,
+    ) -> Result<Self, NostrServiceError> {
+        let delegator_pubkey = parse_pubkey(delegator).ok_or_else(|| {
+            NostrServiceError::DelegationInvalid(format!("invalid delegator pubkey {}", delegator))
+        })?;
+        let conditions = Conditions::from_str(conditions.trim())
+            .map_err(|err| NostrServiceError::DelegationInvalid(err.to_string()))?;
+        let signature = Signature::from_str(signature.trim())
+            .map_err(|err| NostrServiceError::DelegationInvalid(err.to_string()))?;
+
+        Ok(Self {
+            delegator_pubkey,
+            conditions,
+            signature,
+        })
+    }
+
+    /// NIP26 delegation `Tag`
+    pub fn tag(&self) -> Tag {
+        Tag::Delegation {
+            delegator_pk: self.delegator_pubkey,
+            conditions: self.conditions.clone(),
+            sig: self.signature,
+        }
+    }
+}
+
+/// Key an event was signed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SigningPath {
+    /// Signed by the user's key through NIP46 `sign_event`
+    RemoteSigner,
+    /// Signed by the app key with a NIP26 delegation tag
+    Delegation,
+}
+
+impl fmt::Display for SigningPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningPath::RemoteSigner => write!(f, "remote signer"),
+            SigningPath::Delegation => write!(f, "delegation"),
+        }
+    }
+}
+
+/// Encryption scheme for remote signer encrypt and decrypt requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionScheme {
+    /// NIP04 AES-256-CBC
+    Nip04,
+    /// NIP44 versioned encryption
+    Nip44,
+}
+
+impl fmt::Display for EncryptionScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionScheme::Nip04 => write!(f, "nip04"),
+            EncryptionScheme::Nip44 => write!(f, "nip44"),
+        }
+    }
+}
+
+/// Answer of a relay to a published event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayStatus {
+    pub relay: Url,
+    /// Relay answered `OK` with true
+    pub accepted: bool,
+    /// Rejection message or send error
+    pub message: String,
+}
+
+impl RelayStatus {
+    fn new(relay: Url, result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self {
+                relay,
+                accepted: true,
+                message: String::new(),
+            },
+            Err(message) => Self {
+                relay,
+                accepted: false,
+                message,
+            },
+        }
+    }
+}
+
+/// Event published by the service
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedEvent {
+    pub event_id: EventId,
+    pub kind: Kind,
+    pub created_at: Timestamp,
+    pub signing_path: SigningPath,
+    /// Delegator of the delegation tag the event carries
+    pub delegator: Option<XOnlyPublicKey>,
+    pub relays: Vec<RelayStatus>,
+    /// Deletion request published for the event
+    pub deletion: Option<EventId>,
+}
+
+impl PublishedEvent {
+    /// Number of relays that accepted the event
+    pub fn accepted(&self) -> usize {
+        self.relays.iter().filter(|status| status.accepted).count()
+    }
+}
+
+/// Text note waiting for its publish time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledNote {
+    /// Identifies the note to cancel it
+    pub id: u64,
+    pub content: String,
+    pub publish_at: Timestamp,
+    /// Error of the last publish attempt, the note then waits to be retried by hand
+    pub failed: Option<String>,
+}
+
+/// Scheduled notes, kept only while the tab is open
+#[derive(Debug, Default)]
+struct Schedule {
+    next_id: u64,
+    /// Earliest publish time first
+    notes: Vec<ScheduledNote>,
+}
+
+/// Delegation expiry notice from the expiry watcher
+#[derive(Debug, Clone)]
+pub enum DelegationExpiry {
+    /// Delegation expires within the warning window
+    ExpiringSoon(DelegationInfo),
+    /// Delegation expired and was removed
+    Expired(DelegationInfo),
+}
+
+/// NIP04 direct message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectMessage {
+    pub event_id: EventId,
+    /// The other party of the conversation
+    pub counterparty: XOnlyPublicKey,
+    /// Sent by the remote signer's key
+    pub outgoing: bool,
+    /// Encrypted content as sent
+    pub encrypted: String,
+    /// Decrypted content, `None` until decrypted
+    pub content: Option<String>,
+    pub created_at: u64,
+}
+
+/// Text notes of followed pubkeys, newest first and without duplicates
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    follows: Vec<XOnlyPublicKey>,
+    notes: Vec<Event>,
+    seen: HashSet<EventId>,
+    /// An older page came back without new notes
+    exhausted: bool,
+}
+
+impl Timeline {
+    pub fn new(follows: Vec<XOnlyPublicKey>) -> Self {
+        Self {
+            follows,
+            ..Default::default()
+        }
+    }
+
+    pub fn follows(&self) -> &[XOnlyPublicKey] {
+        &self.follows
+    }
+
+    pub fn notes(&self) -> &[Event] {
+        &self.notes
+    }
+
+    /// Whether there may be older notes to load
+    pub fn has_more(&self) -> bool {
+        !self.exhausted && !self.follows.is_empty()
+    }
+
+    /// Add the notes not seen yet, returns how many were added
+    pub fn insert(&mut self, events: Vec<Event>) -> usize {
+        let before = self.notes.len();
+        for event in events {
+            if event.kind == Kind::TextNote && self.seen.insert(event.id) {
+                self.notes.push(event);
+            }
+        }
+        self.notes
+            .sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id)));
+        self.notes.len() - before
+    }
+
+    /// Add an older page, marking the timeline exhausted if nothing was new
+    pub fn insert_older(&mut self, events: Vec<Event>) {
+        if self.insert(events) == 0 {
+            self.exhausted = true;
+        }
+    }
+
+    pub fn newest(&self) -> Option<Timestamp> {
+        self.notes.first().map(|note| note.created_at)
+    }
+
+    pub fn oldest(&self) -> Option<Timestamp> {
+        self.notes.last().map(|note| note.created_at)
+    }
 }
 
 /// Nostr service
@@ -63,8 +410,15 @@ pub struct NostrService {
     keys: Keys,
     client: Arc<Mutex<Client>>,
     connect_relay: Url,
-    relays: DashSet<Url>,
+    relays: Arc<DashSet<Url>>,
     remote_signer: Option<XOnlyPublicKey>,
+    /// Where the session state is persisted, shared with clones so a switch reaches them
+    store: Rc<RefCell<Store>>,
+    /// Account the session belongs to
+    account: Account,
+    /// HTTP client for NIP05 lookups
+    fetcher: Rc<dyn HttpFetcher>,
+    schedule: Rc<RefCell<Schedule>>,
 }
 
 impl NostrService {
@@ -72,9 +426,10 @@ impl NostrService {
         keys: &Keys,
         remote_signer_pubkey: Option<XOnlyPublicKey>,
         connect_relay: Url,
-    ) -> Result<Self> {
-        SessionStorage::set("priv_key", keys.secret_key().unwrap()).expect("failed to set");
-        let relays = DashSet::new();
+        relays: DashSet<Url>,
+        store: Store,
+        account: Account,
+    ) -> Result<Self, NostrServiceError> {
         relays.insert(connect_relay.clone());
 
         let remote_signer = RemoteSigner::new(connect_relay.clone(), remote_signer_pubkey);
@@ -82,10 +437,15 @@ impl NostrService {
         let client = Arc::new(Mutex::new(client));
 
         let client_clone = client.clone();
-        let connect_relay_clone = connect_relay.clone();
+        let relays_clone = relays.clone();
         spawn_local(async move {
             let client = client_clone.lock().await;
-            client.add_relay(connect_relay_clone).await.unwrap();
+            if let Err(err) = client
+                .add_relays(relays_clone.iter().map(|r| r.to_string()).collect())
+                .await
+            {
+                warn!("Could not add relays {}", err);
+            }
             client.connect().await;
         });
 
@@ -93,15 +453,24 @@ impl NostrService {
         Ok(Self {
             client,
             connect_relay,
-            relays,
+            relays: Arc::new(relays),
             keys: keys.clone(),
             remote_signer: remote_signer_pubkey,
+            store: Rc::new(RefCell::new(store)),
+            account,
+            fetcher: Rc::new(BrowserFetcher),
+            schedule: Rc::default(),
         })
     }
 
-    pub fn new_without_remote(keys: &Keys, relays: DashSet<Url>) -> Result<Self> {
-        SessionStorage::set("priv_key", keys.secret_key().unwrap()).expect("failed to set");
-        // TODO: Save relays
+    pub fn new_without_remote(
+        keys: &Keys,
+        connect_relay: Url,
+        relays: DashSet<Url>,
+        store: Store,
+        account: Account,
+    ) -> Result<Self, NostrServiceError> {
+        RelayConfig::new(&connect_relay, &relays).save(&account)?;
         let client = Client::new(keys);
 
         let client = Arc::new(Mutex::new(client));
@@ -123,39 +492,122 @@ impl NostrService {
 
         Ok(Self {
             client,
-            connect_relay: Url::from_str("ws://localhost:8081").unwrap(),
+            connect_relay,
             keys: keys.clone(),
             remote_signer: None,
-            relays,
+            relays: Arc::new(relays),
+            store: Rc::new(RefCell::new(store)),
+            account,
+            fetcher: Rc::new(BrowserFetcher),
+            schedule: Rc::default(),
         })
     }
 
+    /// Export the session as a password encrypted bundle
+    pub fn export_bundle(&self, password: &str) -> Result<String, NostrServiceError> {
+        SessionBundle::new(
+            &self.keys,
+            password,
+            self.remote_signer,
+            self.connect_relay.to_string(),
+            self.relays.iter().map(|r| r.to_string()).collect(),
+            self.get_delegations()?,
+        )?
+        .to_json()
+    }
+
+    /// Restore a session from a bundle created by [`NostrService::export_bundle`] into `account`
+    ///
+    /// The bundle is checked before anything is written, `account` is expected to be new
+    /// as its app key, relays and delegations are replaced
+    pub fn import_bundle(
+        bundle: &str,
+        password: &str,
+        store: Store,
+        account: Account,
+    ) -> Result<Self, NostrServiceError> {
+        let bundle = SessionBundle::from_json(bundle)?;
+        let keys = bundle.keys(password)?;
+        for delegation in &bundle.delegations {
+            delegation.verify_for(keys.public_key())?;
+        }
+
+        // The bundle password unlocks the account from now on
+        store.set_ncryptsec(&bundle.ncryptsec)?;
+        store.set_delegations(&bundle.delegations)?;
+        if let Some(remote_signer) = bundle.remote_signer {
+            store.set_remote_signer(&remote_signer)?;
+        }
+        let relay_config = RelayConfig {
+            connect_relay: bundle.connect_relay,
+            relays: bundle.relays,
+            ..Default::default()
+        };
+        relay_config.save(&account)?;
+
+        let mut service = if bundle.delegations.is_empty() {
+            Self::new(
+                &keys,
+                bundle.remote_signer,
+                relay_config.connect_relay(),
+                relay_config.relays(),
+                store,
+                account,
+            )?
+        } else {
+            Self::new_without_remote(
+                &keys,
+                relay_config.connect_relay(),
+                relay_config.relays(),
+                store,
+                account,
+            )?
+        };
+
+        service.set_remote_pubkey(bundle.remote_signer);
+
+        Ok(service)
+    }
+
     /// Add new relay to client
-    pub fn add_relay(&self, relay: Url) -> Result<()> {
-        let client = self.client.clone();
+    pub async fn add_relay(&self, relay: Url) -> Result<(), NostrServiceError> {
         self.relays.insert(relay.clone());
-        spawn_local(async move {
-            let client = client.lock().await;
-            client.add_relay(relay).await.ok();
-            client.connect().await;
-        });
+        self.save_relay_config()?;
+        let client = self.client.lock().await;
+        client.add_relay(relay).await?;
+        client.connect().await;
         Ok(())
     }
 
     /// Remove relay
-    pub fn remove_relay(&mut self, relay: Url) {
-        let client = self.client.clone();
+    pub async fn remove_relay(&self, relay: Url) -> Result<(), NostrServiceError> {
         self.relays.remove(&relay);
-        spawn_local(async move {
-            let client = client.lock().await;
-            client.remove_relay(relay).await.ok();
-        });
+        self.save_relay_config()?;
+        let client = self.client.lock().await;
+        client.remove_relay(relay).await?;
+        Ok(())
+    }
+
+    /// Disconnect from every relay and stop the client, used when the session ends
+    pub async fn shutdown(&self) {
+        let client = self.client.lock().await.clone();
+        if let Err(err) = client.shutdown().await {
+            warn!("Could not shut down client {}", err);
+        }
     }
 
     /// Set connect relay
-    pub fn set_connect_relay(&mut self, relay: Url) {
+    pub fn set_connect_relay(&mut self, relay: Url) -> Result<(), NostrServiceError> {
         debug!("Setting connect relay");
+        // The signer answers on the connect relay, so it is read and published to as well
+        self.relays.insert(relay.clone());
         self.connect_relay = relay;
+        self.save_relay_config()
+    }
+
+    /// Persist connect relay and publish relays
+    fn save_relay_config(&self) -> Result<(), NostrServiceError> {
+        RelayConfig::new(&self.connect_relay, &self.relays).save(&self.account)
     }
 
     /// Get connect relay
@@ -174,142 +626,504 @@ impl NostrService {
     }
 
     /// Create a new nostr client with a remote signer
-    pub fn new_client_with_remote_signer(&mut self) {
-        let client = self.client.clone();
-        let connect_relay = self.connect_relay.clone();
-
-        spawn_local(async move {
-            let mut client = client.lock().await;
-            let keys = client.keys();
-            let remote_signer = RemoteSigner::new(connect_relay, None);
-            let new_client = Client::with_remote_signer(&keys, remote_signer);
-            client.connect().await;
-            *client = new_client;
-        });
+    pub async fn new_client_with_remote_signer(&self) -> Result<(), NostrServiceError> {
+        let mut client = self.client.lock().await;
+        let keys = client.keys();
+        let remote_signer = RemoteSigner::new(self.connect_relay.clone(), None);
+        let new_client = Client::with_remote_signer(&keys, remote_signer);
+        new_client
+            .add_relays(self.relays.iter().map(|r| r.to_string()).collect())
+            .await?;
+        if !self.relays.contains(&self.connect_relay) {
+            new_client.add_relay(self.connect_relay.clone()).await?;
+        }
+        new_client.connect().await;
+        *client = new_client;
+        Ok(())
     }
 
     /// Create a new nostr client without a remote signer
-    pub fn create_client(&mut self, new_relays: DashSet<Url>) -> Result<()> {
-        let client = self.client.clone();
-        let mut relays = self.relays.clone();
-        relays.extend(new_relays);
-        spawn_local(async move {
-            let mut client = client.lock().await;
-            let keys = client.keys();
-            let new_client = Client::new(&keys);
-            if (new_client.add_relays(relays.into_iter().collect()).await).is_ok() {
-                new_client.connect().await;
-                *client = new_client;
-            } else {
-                warn!("Could not create new client")
-            }
-        });
+    pub async fn create_client(&self, new_relays: DashSet<Url>) -> Result<(), NostrServiceError> {
+        for relay in new_relays {
+            self.relays.insert(relay);
+        }
+        self.save_relay_config()?;
+
+        let mut client = self.client.lock().await;
+        let keys = client.keys();
+        let new_client = Client::new(&keys);
+        new_client
+            .add_relays(self.relays.iter().map(|r| r.to_string()).collect())
+            .await?;
+        new_client.connect().await;
+        *client = new_client;
 
         Ok(())
     }
 
     /// Get delegation from remote signer
-    pub fn get_delegate(
+    pub async fn get_delegate(
+        &self,
+        conditions: Conditions,
+    ) -> Result<DelegationInfo, NostrServiceError> {
+        let req = Request::Delegate {
+            public_key: self.keys.public_key(),
+            conditions,
+        };
+
+        let delegation_info = match self.send_signer_req(req).await? {
+            Response::Delegate(delegation_result) => DelegationInfo {
+                delegator_pubkey: delegation_result.from,
+                conditions: delegation_result.cond,
+                signature: delegation_result.sig,
+            },
+            _ => return Err(NostrServiceError::UnexpectedResponse),
+        };
+
+        self.verify_delegation(&delegation_info)?;
+
+        Ok(delegation_info)
+    }
+
+    /// Check delegation signature is valid for the app pubkey
+    fn verify_delegation(&self, delegation_info: &DelegationInfo) -> Result<(), NostrServiceError> {
+        delegation_info.verify_for(self.keys.public_key())
+    }
+
+    /// Add a delegation to the app delegations
+    pub fn add_delegation_info(
         &mut self,
-        expiration_unix_time: u64,
-        kinds: Vec<u64>,
-        callback: Callback<AttrValue>,
-        delegation_info_cb: Callback<DelegationInfo>,
-    ) -> Result<()> {
-        let client = self.client.clone();
+        delegation_info: DelegationInfo,
+    ) -> Result<(), NostrServiceError> {
+        self.verify_delegation(&delegation_info)?;
 
-        spawn_local(async move {
-            let client = client.lock().await;
-            let pubkey = client.keys().public_key();
+        let mut delegations = self.get_delegations()?;
+        delegations.retain(|d| d.signature != delegation_info.signature);
+        delegations.push(delegation_info);
+
+        self.save_delegations(&delegations)
+    }
+
+    /// Remove a delegation by its signature
+    pub fn remove_delegation_info(&self, signature: &Signature) -> Result<(), NostrServiceError> {
+        let mut delegations = self.get_delegations()?;
+        delegations.retain(|d| &d.signature != signature);
+
+        self.save_delegations(&delegations)
+    }
+
+    fn save_delegations(&self, delegations: &[DelegationInfo]) -> Result<(), NostrServiceError> {
+        self.store().set_delegations(delegations)
+    }
+
+    /// Pubkeys events are published for, the remote signer and delegators
+    pub fn signer_pubkeys(&self) -> Vec<XOnlyPublicKey> {
+        let mut pubkeys: Vec<XOnlyPublicKey> = self
+            .get_delegations()
+            .unwrap_or_default()
+            .iter()
+            .map(|delegation| delegation.delegator_pubkey)
+            .collect();
+        pubkeys.extend(self.remote_signer);
+        pubkeys.sort();
+        pubkeys.dedup();
+        pubkeys
+    }
+
+    /// Cached kind 0 metadata of `pubkey`
+    pub fn get_metadata(&self, pubkey: &XOnlyPublicKey) -> Option<Metadata> {
+        self.store().metadata().remove(pubkey)
+    }
+
+    /// Fetch the newest kind 0 metadata of the signer and delegators and cache it
+    pub async fn fetch_metadata(&self) -> Result<(), NostrServiceError> {
+        self.fetch_metadata_of(self.signer_pubkeys()).await?;
+        Ok(())
+    }
+
+    /// Fetch the newest kind 0 metadata of `pubkeys` and cache it
+    ///
+    /// Returns the pubkeys relays sent metadata for
+    async fn fetch_metadata_of(
+        &self,
+        pubkeys: Vec<XOnlyPublicKey>,
+    ) -> Result<HashSet<XOnlyPublicKey>, NostrServiceError> {
+        let mut fetched = HashSet::new();
+        if pubkeys.is_empty() {
+            return Ok(fetched);
+        }
 
-            let mut conditions = Conditions::new();
-            // Set valid from time as current time
-            conditions.add(Condition::CreatedAfter(Timestamp::now().as_u64()));
-            conditions.add(Condition::CreatedBefore(expiration_unix_time));
+        let filter = Filter::new().kind(Kind::Metadata).authors(pubkeys);
+        let events = self
+            .client
+            .lock()
+            .await
+            .get_events_of(vec![filter], Some(FETCH_TIMEOUT))
+            .await?;
 
-            for kind in kinds {
-                conditions.add(Condition::Kind(kind));
+        let mut newest: HashMap<XOnlyPublicKey, Event> = HashMap::new();
+        for event in events {
+            match newest.get(&event.pubkey) {
+                Some(known) if known.created_at >= event.created_at => {}
+                _ => {
+                    newest.insert(event.pubkey, event);
+                }
             }
+        }
 
-            let req = Request::Delegate {
-                public_key: pubkey,
-                conditions,
-            };
-            match client.send_req_to_signer(req, None).await {
-                Ok(res) => {
-                    if let Response::Delegate(delegation_result) = res {
-                        let delegation_info = DelegationInfo {
-                            delegator_pubkey: delegation_result.from,
-                            conditions: delegation_result.cond,
-                            signature: delegation_result.sig,
-                        };
-
-                        delegation_info_cb.emit(delegation_info);
-
-                        callback.emit("".into());
-                    }
+        let mut cache = self.store().metadata();
+        for (pubkey, event) in newest {
+            match serde_json::from_str::<Metadata>(&event.content) {
+                Ok(metadata) => {
+                    cache.insert(pubkey, metadata);
+                    fetched.insert(pubkey);
                 }
-                Err(err) => error!("Get delegation error: {}", err),
+                Err(err) => warn!("Invalid metadata {}: {}", event.id, err),
             }
-        });
+        }
 
-        Ok(())
+        self.store().set_metadata(&cache)?;
+        Ok(fetched)
     }
 
-    /// Set the app delegation info
-    pub fn set_delegation_info(&mut self, delegation_info: DelegationInfo) -> Result<()> {
-        SessionStorage::set("delegationInfo", serde_json::to_string(&delegation_info)?)?;
-        Ok(())
+    /// Pubkey the profile is published for
+    ///
+    /// The delegator of a delegation covering kind 0, otherwise the remote signer
+    pub fn profile_pubkey(&self) -> Option<XOnlyPublicKey> {
+        match self.delegation_for(Kind::Metadata, Timestamp::now()) {
+            Ok(Some(delegation)) => Some(delegation.delegator_pubkey),
+            _ => self.remote_signer.or_else(|| {
+                self.get_delegations()
+                    .ok()?
+                    .first()
+                    .map(|delegation| delegation.delegator_pubkey)
+            }),
+        }
+    }
+
+    /// Fetch the current kind 0 metadata of the profile pubkey
+    ///
+    /// `None` if no relay sent one, either there is no profile yet or it could not be read
+    pub async fn load_profile(&self) -> Result<Option<Metadata>, NostrServiceError> {
+        let pubkey = self
+            .profile_pubkey()
+            .ok_or(NostrServiceError::SignerNotSet)?;
+        if !self
+            .fetch_metadata_of(vec![pubkey])
+            .await?
+            .contains(&pubkey)
+        {
+            return Ok(None);
+        }
+
+        Ok(self.get_metadata(&pubkey))
     }
 
-    /// Get the app delegation info
-    pub fn get_delegation_info(&self) -> Result<Option<DelegationInfo>> {
-        if let Ok(Some(info)) = SessionStorage::get::<Option<String>>("delegationInfo") {
-            let delegation_info: DelegationInfo = serde_json::from_str(info.as_str())?;
+    /// Publish kind 0 metadata and cache it for the profile pubkey
+    pub async fn publish_metadata(
+        &self,
+        metadata: Metadata,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let pubkey = self
+            .profile_pubkey()
+            .ok_or(NostrServiceError::SignerNotSet)?;
+        let content = serde_json::to_string(&metadata)?;
+        let (event, signing_path) = self.sign_event(Kind::Metadata, &content, vec![]).await?;
+        let published = self.publish_event(event, signing_path).await?;
 
-            if verify_delegation_signature(
-                delegation_info.delegator_pubkey,
-                delegation_info.signature,
-                self.keys.public_key(),
-                delegation_info.conditions.clone(),
-            )
-            .is_ok()
+        let mut cache = self.store().metadata();
+        cache.insert(pubkey, metadata);
+        self.store().set_metadata(&cache)?;
+
+        Ok(published)
+    }
+
+    /// Get the app delegations with a valid signature
+    pub fn get_delegations(&self) -> Result<Vec<DelegationInfo>, NostrServiceError> {
+        Ok(self
+            .store()
+            .delegations()?
+            .into_iter()
+            .filter(|delegation| match self.verify_delegation(delegation) {
+                Ok(()) => true,
+                Err(err) => {
+                    warn!("Ignoring delegation: {}", err);
+                    false
+                }
+            })
+            .collect())
+    }
+
+    /// Check if the app holds any delegations
+    pub fn has_delegations(&self) -> bool {
+        matches!(self.get_delegations(), Ok(delegations) if !delegations.is_empty())
+    }
+
+    /// Find a delegation that covers an event of `kind` created at `created_at`
+    pub fn delegation_for(
+        &self,
+        kind: Kind,
+        created_at: Timestamp,
+    ) -> Result<Option<DelegationInfo>, NostrServiceError> {
+        let delegations = self.get_delegations()?;
+        if delegations.is_empty() {
+            return Ok(None);
+        }
+
+        let mut reasons = Vec::with_capacity(delegations.len());
+        for delegation in &delegations {
+            match delegation.validate_for(kind, created_at) {
+                Ok(()) => return Ok(Some(delegation.clone())),
+                Err(err) => reasons.push(err),
+            }
+        }
+
+        // Suggest renewing the delegation that was closest to covering the event
+        let renewal = delegations
+            .iter()
+            .zip(&reasons)
+            .find(|(_, reason)| matches!(reason, DelegationError::Expired(_)))
+            .map(|(delegation, _)| delegation)
+            .unwrap_or(&delegations[0])
+            .renewal_conditions(Some(kind));
+
+        Err(NostrServiceError::DelegationUnsatisfied {
+            kind: kind.as_u64(),
+            reasons,
+            renewal,
+        })
+    }
+
+    /// Timer for [`NostrService::check_delegation_expiry`]
+    ///
+    /// Holds no reference to the service so locking drops the keys
+    pub fn delegation_expiry_timer() -> impl Stream<Item = ()> {
+        IntervalStream::new(EXPIRY_CHECK_INTERVAL)
+    }
+
+    /// Timer for polling the timeline for new notes
+    ///
+    /// Polled rather than subscribed, the relay pool subscription is used by the remote signer
+    pub fn timeline_timer() -> impl Stream<Item = ()> {
+        IntervalStream::new(TIMELINE_POLL_INTERVAL)
+    }
+
+    /// Timer for [`NostrService::process_outbox`]
+    pub fn outbox_timer() -> impl Stream<Item = ()> {
+        IntervalStream::new(OUTBOX_CHECK_INTERVAL)
+    }
+
+    /// Timer for [`NostrService::take_due_notes`]
+    pub fn schedule_timer() -> impl Stream<Item = ()> {
+        IntervalStream::new(SCHEDULE_CHECK_INTERVAL)
+    }
+
+    /// Pubkeys in the newest kind 3 contact list of the user
+    pub async fn get_follows(&self) -> Result<Vec<XOnlyPublicKey>, NostrServiceError> {
+        let pubkey = self
+            .remote_signer
+            .or_else(|| self.profile_pubkey())
+            .ok_or(NostrServiceError::SignerNotSet)?;
+
+        let filter = Filter::new().kind(Kind::ContactList).author(pubkey);
+        let events = self
+            .client
+            .lock()
+            .await
+            .get_events_of(vec![filter], Some(FETCH_TIMEOUT))
+            .await?;
+
+        let follows = match events.into_iter().max_by_key(|event| event.created_at) {
+            Some(contact_list) => contact_list
+                .tags
+                .iter()
+                .filter_map(|tag| match tag {
+                    Tag::PubKey(pubkey, _) => Some(*pubkey),
+                    _ => None,
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        Ok(follows)
+    }
+
+    /// Fetch a page of text notes by `authors` created within `since` and `until`
+    pub async fn get_notes(
+        &self,
+        authors: Vec<XOnlyPublicKey>,
+        since: Option<Timestamp>,
+        until: Option<Timestamp>,
+    ) -> Result<Vec<Event>, NostrServiceError> {
+        if authors.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut filter = Filter::new()
+            .kind(Kind::TextNote)
+            .authors(authors)
+            .limit(TIMELINE_PAGE_SIZE);
+        if let Some(since) = since {
+            filter = filter.since(since);
+        }
+        if let Some(until) = until {
+            filter = filter.until(until);
+        }
+
+        Ok(self
+            .client
+            .lock()
+            .await
+            .get_events_of(vec![filter], Some(FETCH_TIMEOUT))
+            .await?)
+    }
+
+    /// Load the contact list and the newest page of notes of followed pubkeys
+    pub async fn load_timeline(&self) -> Result<Timeline, NostrServiceError> {
+        let mut timeline = Timeline::new(self.get_follows().await?);
+        let notes = self
+            .get_notes(timeline.follows().to_vec(), None, None)
+            .await?;
+        timeline.insert(notes);
+
+        Ok(timeline)
+    }
+
+    /// Remove expired delegations and report those expiring soon that have not been reported yet
+    pub fn check_delegation_expiry(&self, warned: &mut HashSet<String>) -> Vec<DelegationExpiry> {
+        let delegations = match self.get_delegations() {
+            Ok(delegations) => delegations,
+            Err(err) => {
+                warn!("Could not check delegations: {}", err);
+                return vec![];
+            }
+        };
+
+        let now = Timestamp::now().as_u64();
+        let mut notices = vec![];
+        for delegation in delegations {
+            let before = match delegation.created_before() {
+                Some(before) => before,
+                None => continue,
+            };
+
+            if before <= now {
+                if let Err(err) = self.remove_delegation_info(&delegation.signature) {
+                    warn!("Could not remove expired delegation: {}", err);
+                }
+                notices.push(DelegationExpiry::Expired(delegation));
+            } else if before - now <= EXPIRY_WARNING
+                && warned.insert(delegation.signature.to_string())
             {
-                return Ok(Some(delegation_info));
+                notices.push(DelegationExpiry::ExpiringSoon(delegation));
             }
         }
-        Ok(None)
+
+        notices
+    }
+
+    /// Replace the client with one using the known remote signer
+    pub async fn restore_remote_signer(&self) -> Result<(), NostrServiceError> {
+        let signer_pubkey = self.remote_signer.ok_or(NostrServiceError::SignerNotSet)?;
+        let mut client = self.client.lock().await;
+        self.replace_with_remote_signer_client(&mut client, signer_pubkey)
+            .await
+    }
+
+    async fn replace_with_remote_signer_client(
+        &self,
+        client: &mut Client,
+        signer_pubkey: XOnlyPublicKey,
+    ) -> Result<(), NostrServiceError> {
+        let remote_signer = RemoteSigner::new(self.connect_relay.clone(), Some(signer_pubkey));
+        let new_client = Client::with_remote_signer(&self.keys, remote_signer);
+        new_client
+            .add_relays(self.relays.iter().map(|r| r.to_string()).collect())
+            .await?;
+        new_client.connect().await;
+        *client = new_client;
+        Ok(())
     }
 
     /// Wait for pubkey of signer
-    pub fn req_signer_pub_key(&self, callback: Callback<Option<XOnlyPublicKey>>) -> Result<()> {
-        let client = self.client.clone();
-        spawn_local(async move {
-            debug!("Waiting for pubkey");
-            let client = client.lock().await;
+    pub async fn req_signer_pub_key(&self) -> Result<XOnlyPublicKey, NostrServiceError> {
+        debug!("Waiting for pubkey");
+        let client = self.client.lock().await;
 
-            client.connect().await;
+        client.connect().await;
+        client
+            .req_signer_public_key(Some(SIGNER_TIMEOUT))
+            .await
+            .map_err(NostrServiceError::from_signer)?;
 
-            match client.req_signer_public_key(None).await {
-                Ok(_) => {
-                    let remote = client.remote_signer().unwrap().signer_public_key().await;
+        let remote = client
+            .remote_signer()
+            .ok_or(NostrServiceError::SignerNotSet)?
+            .signer_public_key()
+            .await
+            .ok_or(NostrServiceError::SignerNotSet)?;
 
-                    if let Some(remote) = remote {
-                        if let Err(err) = SessionStorage::set("remote_pub_key", remote.to_string())
-                        {
-                            warn!("Could not set remote pubkey {}", err);
-                        }
-                    }
-                    callback.emit(remote);
-                }
-                Err(err) => {
-                    warn!("Could not set signer key {}", err);
+        if let Err(err) = self.store().set_remote_signer(&remote) {
+            warn!("Could not set remote pubkey {}", err);
+        }
+
+        debug!("Set signer key");
+        Ok(remote)
+    }
+
+    /// Use `fetcher` for NIP05 lookups
+    pub fn set_fetcher(&mut self, fetcher: Rc<dyn HttpFetcher>) {
+        self.fetcher = fetcher;
+    }
+
+    /// Parse a pubkey from npub, hex or a NIP05 identifier
+    pub async fn resolve_pubkey(&self, input: &str) -> Result<XOnlyPublicKey, NostrServiceError> {
+        if let Some(pubkey) = parse_pubkey(input) {
+            return Ok(pubkey);
+        }
+        if !nip05::is_identifier(input) {
+            return Err(NostrServiceError::InvalidPubkey(input.trim().to_string()));
+        }
+
+        Ok(nip05::resolve(self.fetcher.as_ref(), input).await?)
+    }
+
+    /// Resolve and save the pubkey of a remote signer added by hand
+    pub async fn resolve_signer(&self, input: &str) -> Result<XOnlyPublicKey, NostrServiceError> {
+        let pubkey = self.resolve_pubkey(input).await?;
+        self.store().set_remote_signer(&pubkey)?;
+        Ok(pubkey)
+    }
+
+    /// Signer and delegator pubkeys whose cached NIP05 identifier checks out
+    pub async fn verify_nip05(&self) -> HashSet<XOnlyPublicKey> {
+        let metadata = self.store().metadata();
+        let mut verified = HashSet::new();
+        for pubkey in self.signer_pubkeys() {
+            let identifier = match metadata.get(&pubkey).and_then(|m| m.nip05.as_ref()) {
+                Some(identifier) => identifier,
+                None => continue,
+            };
+
+            match nip05::verify(self.fetcher.as_ref(), identifier, &pubkey).await {
+                Ok(true) => {
+                    verified.insert(pubkey);
                 }
+                Ok(false) => warn!("{} does not list {}", identifier, pubkey),
+                Err(err) => warn!("Could not verify {}: {}", identifier, err),
             }
-            debug!("Set signer key");
-        });
+        }
+        verified
+    }
 
-        Ok(())
+    /// Store the session state is persisted in
+    fn store(&self) -> Store {
+        self.store.borrow().clone()
+    }
+
+    /// Persist the session state in `store` from now on, in every clone of the service
+    pub fn set_store(&self, store: Store) {
+        *self.store.borrow_mut() = store;
     }
 
     /// Set remote signer pubkey
@@ -322,47 +1136,654 @@ impl NostrService {
         self.remote_signer
     }
 
-    /// Create delegation `Tag` from service delegation info
-    fn delegation_tag(&self) -> Result<Option<Tag>> {
-        let delegation_info = self.get_delegation_info()?;
+    /// Sign an event with a matching delegation if there are any, otherwise with the remote signer
+    pub async fn sign_event(
+        &self,
+        kind: Kind,
+        content: &str,
+        mut tags: Vec<Tag>,
+    ) -> Result<(Event, SigningPath), NostrServiceError> {
+        // Refuse to fall back to the remote signer if delegations exist but none match
+        if let Some(delegation) = self.delegation_for(kind, Timestamp::now())? {
+            tags.push(delegation.tag());
+            let event = EventBuilder::new(kind, content, &tags).to_event(&self.keys)?;
+            return Ok((event, SigningPath::Delegation));
+        }
 
-        let tag = delegation_info.map(|delegation| Tag::Delegation {
-            delegator_pk: delegation.delegator_pubkey,
-            conditions: delegation.conditions.clone(),
-            sig: delegation.signature,
-        });
+        let signer_pubkey = self.remote_signer.ok_or(NostrServiceError::SignerNotSet)?;
+        let event = self
+            .remote_sign_event(
+                EventBuilder::new(kind, content, &tags).to_unsigned_event(signer_pubkey),
+            )
+            .await?;
+
+        Ok((event, SigningPath::RemoteSigner))
+    }
 
-        Ok(tag)
+    /// Sign an event with the key of `signing_path`
+    ///
+    /// Delegated events are signed by the app key, the delegation tag is only
+    /// added when a delegation covers `kind`
+    async fn sign_event_as(
+        &self,
+        signing_path: SigningPath,
+        kind: Kind,
+        content: &str,
+        mut tags: Vec<Tag>,
+    ) -> Result<Event, NostrServiceError> {
+        match signing_path {
+            SigningPath::Delegation => {
+                if let Ok(Some(delegation)) = self.delegation_for(kind, Timestamp::now()) {
+                    tags.push(delegation.tag());
+                }
+                Ok(EventBuilder::new(kind, content, &tags).to_event(&self.keys)?)
+            }
+            SigningPath::RemoteSigner => {
+                let signer_pubkey = self.remote_signer.ok_or(NostrServiceError::SignerNotSet)?;
+                self.remote_sign_event(
+                    EventBuilder::new(kind, content, &tags).to_unsigned_event(signer_pubkey),
+                )
+                .await
+            }
+        }
+    }
+
+    /// Send a NIP46 `sign_event` request to the remote signer
+    async fn remote_sign_event(
+        &self,
+        unsigned_event: UnsignedEvent,
+    ) -> Result<Event, NostrServiceError> {
+        let req = Request::SignEvent(unsigned_event.clone());
+
+        match self.send_signer_req(req).await? {
+            Response::SignEvent(sig) => unsigned_event
+                .add_signature(sig)
+                .map_err(|err| NostrServiceError::SignerRejected(err.to_string())),
+            _ => Err(NostrServiceError::UnexpectedResponse),
+        }
+    }
+
+    /// Encrypt `text` for `public_key` with the remote signer's key
+    pub async fn encrypt(
+        &self,
+        scheme: EncryptionScheme,
+        public_key: XOnlyPublicKey,
+        text: &str,
+    ) -> Result<String, NostrServiceError> {
+        let req = match scheme {
+            EncryptionScheme::Nip04 => Request::Nip04Encrypt {
+                public_key,
+                text: text.to_string(),
+            },
+            // The NIP46 implementation in nostr-sdk does not expose nip44 methods yet
+            EncryptionScheme::Nip44 => return Err(NostrServiceError::Unsupported(scheme)),
+        };
+
+        match self.send_signer_req(req).await? {
+            Response::Nip04Encrypt(encrypted) => Ok(encrypted),
+            _ => Err(NostrServiceError::UnexpectedResponse),
+        }
+    }
+
+    /// Decrypt `text` from `public_key` with the remote signer's key
+    pub async fn decrypt(
+        &self,
+        scheme: EncryptionScheme,
+        public_key: XOnlyPublicKey,
+        text: &str,
+    ) -> Result<String, NostrServiceError> {
+        let req = match scheme {
+            EncryptionScheme::Nip04 => Request::Nip04Decrypt {
+                public_key,
+                text: text.to_string(),
+            },
+            EncryptionScheme::Nip44 => return Err(NostrServiceError::Unsupported(scheme)),
+        };
+
+        match self.send_signer_req(req).await? {
+            Response::Nip04Decrypt(decrypted) => Ok(decrypted),
+            _ => Err(NostrServiceError::UnexpectedResponse),
+        }
+    }
+
+    /// Send a request to the remote signer
+    async fn send_signer_req(&self, req: Request) -> Result<Response, NostrServiceError> {
+        let signer_pubkey = self.remote_signer.ok_or(NostrServiceError::SignerNotSet)?;
+
+        let mut client = self.client.lock().await;
+
+        // The client is replaced by one without a remote signer once a delegation is set,
+        // or the signer may have been added by hand after the client was created
+        let client_signer = match client.remote_signer() {
+            Some(remote_signer) => remote_signer.signer_public_key().await,
+            None => None,
+        };
+        if client_signer != Some(signer_pubkey) {
+            self.replace_with_remote_signer_client(&mut client, signer_pubkey)
+                .await?;
+        }
+
+        // Wait for the answer without holding the lock so other requests are not stalled
+        let signer_client = client.clone();
+        drop(client);
+        signer_client
+            .send_req_to_signer(req, Some(SIGNER_TIMEOUT))
+            .await
+            .map_err(NostrServiceError::from_signer)
     }
 
     /// Publish a text note
-    pub fn publish_text_note(&self, content: &str, callback: Callback<AttrValue>) -> Result<()> {
-        let client = self.client.clone();
-        let content = content.to_owned();
-        let delegation_tag = self.delegation_tag();
-        debug!("Tet: {:?}", delegation_tag);
-        spawn_local(async move {
-            let tag = match delegation_tag {
-                Ok(Some(tag)) => {
-                    vec![tag]
+    pub async fn publish_text_note(
+        &self,
+        content: &str,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let (event, signing_path) = self.sign_event(Kind::TextNote, content, vec![]).await?;
+
+        self.publish_event(event, signing_path).await
+    }
+
+    /// Schedule a text note to be published at `publish_at`
+    ///
+    /// When delegations are held one must cover the publish time, as the note is signed then
+    pub fn schedule_text_note(
+        &self,
+        content: &str,
+        publish_at: Timestamp,
+    ) -> Result<ScheduledNote, NostrServiceError> {
+        if publish_at <= Timestamp::now() {
+            return Err(NostrServiceError::ScheduledInPast(publish_at.as_u64()));
+        }
+        // Same policy as `sign_event`, checked for the publish time
+        if self.delegation_for(Kind::TextNote, publish_at)?.is_none()
+            && self.remote_signer.is_none()
+        {
+            return Err(NostrServiceError::SignerNotSet);
+        }
+
+        let mut schedule = self.schedule.borrow_mut();
+        schedule.next_id += 1;
+        let note = ScheduledNote {
+            id: schedule.next_id,
+            content: content.to_string(),
+            publish_at,
+            failed: None,
+        };
+        let index = schedule
+            .notes
+            .partition_point(|scheduled| scheduled.publish_at <= publish_at);
+        schedule.notes.insert(index, note.clone());
+
+        Ok(note)
+    }
+
+    /// Notes waiting for their publish time, earliest first
+    pub fn get_scheduled(&self) -> Vec<ScheduledNote> {
+        self.schedule.borrow().notes.clone()
+    }
+
+    pub fn cancel_scheduled(&self, id: u64) {
+        self.schedule
+            .borrow_mut()
+            .notes
+            .retain(|scheduled| scheduled.id != id);
+    }
+
+    /// Remove and return the scheduled notes whose publish time has come, failed notes are kept
+    pub fn take_due_notes(&self) -> Vec<ScheduledNote> {
+        let now = Timestamp::now();
+        let mut schedule = self.schedule.borrow_mut();
+        let due = schedule
+            .notes
+            .partition_point(|scheduled| scheduled.publish_at <= now);
+        let (due, failed): (Vec<_>, Vec<_>) = schedule
+            .notes
+            .drain(..due)
+            .partition(|scheduled| scheduled.failed.is_none());
+        schedule.notes.splice(0..0, failed);
+        due
+    }
+
+    /// Put back a due note that could not be published, until it is retried or cancelled
+    pub fn fail_scheduled(&self, mut note: ScheduledNote, message: String) {
+        note.failed = Some(message);
+        let mut schedule = self.schedule.borrow_mut();
+        let index = schedule
+            .notes
+            .partition_point(|scheduled| scheduled.publish_at <= note.publish_at);
+        schedule.notes.insert(index, note);
+    }
+
+    /// Publish a failed note again on the next schedule check
+    pub fn retry_scheduled(&self, id: u64) {
+        if let Some(note) = self
+            .schedule
+            .borrow_mut()
+            .notes
+            .iter_mut()
+            .find(|scheduled| scheduled.id == id)
+        {
+            note.failed = None;
+        }
+    }
+
+    /// Publish a text note replying to `parent`, tagged as described in NIP10
+    pub async fn publish_reply(
+        &self,
+        content: &str,
+        parent: &Event,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let tags = nip10::reply_tags(parent);
+        let (event, signing_path) = self.sign_event(Kind::TextNote, content, tags).await?;
+
+        self.publish_event(event, signing_path).await
+    }
+
+    /// Publish a text note quoting `quoted`, linked at the end of the content
+    pub async fn publish_quote(
+        &self,
+        content: &str,
+        quoted: &Event,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let note = quoted.id.to_bech32().unwrap_or_else(|_| quoted.id.to_hex());
+        let content = format!("{}\n\nnostr:{}", content.trim_end(), note);
+        let tags = nip10::quote_tags(quoted);
+        let (event, signing_path) = self.sign_event(Kind::TextNote, &content, tags).await?;
+
+        self.publish_event(event, signing_path).await
+    }
+
+    /// Publish a NIP09 deletion of `published`, signed by the key that signed it
+    pub async fn delete_event(
+        &self,
+        published: &PublishedEvent,
+        reason: &str,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let tags = vec![
+            Tag::Event(published.event_id, None, None),
+            Tag::Generic(
+                TagKind::Custom("k".to_string()),
+                vec![published.kind.as_u64().to_string()],
+            ),
+        ];
+        let event = self
+            .sign_event_as(
+                published.signing_path,
+                Kind::EventDeletion,
+                reason.trim(),
+                tags,
+            )
+            .await?;
+
+        let deletion = self.publish_event(event, published.signing_path).await?;
+        self.update_history(|history| {
+            if let Some(deleted) = history
+                .iter_mut()
+                .find(|deleted| deleted.event_id == published.event_id)
+            {
+                deleted.deletion = Some(deletion.event_id);
+            }
+        });
+
+        Ok(deletion)
+    }
+
+    /// Publish a NIP25 reaction to `target`
+    pub async fn publish_reaction(
+        &self,
+        target: &Event,
+        reaction: &Reaction,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let (event, signing_path) = self
+            .sign_event(Kind::Reaction, &reaction.content(), reaction.tags(target))
+            .await?;
+
+        self.publish_event(event, signing_path).await
+    }
+
+    /// Publish a NIP18 repost of `target` with the reposted event as content
+    pub async fn publish_repost(
+        &self,
+        target: &Event,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let (kind, tags) = interactions::repost_tags(target);
+        let content = serde_json::to_string(target)?;
+        let (event, signing_path) = self.sign_event(kind, &content, tags).await?;
+
+        self.publish_event(event, signing_path).await
+    }
+
+    /// Fetch reactions and reposts of `ids`
+    pub async fn get_interactions(
+        &self,
+        ids: Vec<EventId>,
+    ) -> Result<Vec<Event>, NostrServiceError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let filter = Filter::new()
+            .kinds(interactions::interaction_kinds())
+            .events(ids);
+        Ok(self
+            .client
+            .lock()
+            .await
+            .get_events_of(vec![filter], Some(FETCH_TIMEOUT))
+            .await?)
+    }
+
+    /// Fetch the thread `event_id` belongs to, depth first with the depth of each reply
+    pub async fn get_thread(
+        &self,
+        event_id: EventId,
+    ) -> Result<Vec<(usize, Event)>, NostrServiceError> {
+        let client = self.client.lock().await;
+
+        let root = client
+            .get_events_of(
+                vec![Filter::new().id(event_id.to_hex())],
+                Some(FETCH_TIMEOUT),
+            )
+            .await?
+            .first()
+            .and_then(nip10::root_id)
+            .unwrap_or(event_id);
+
+        let filters = vec![
+            Filter::new().id(root.to_hex()),
+            Filter::new().kind(Kind::TextNote).event(root),
+        ];
+        let mut seen = HashSet::new();
+        let events = client
+            .get_events_of(filters, Some(FETCH_TIMEOUT))
+            .await?
+            .into_iter()
+            .filter(|event| seen.insert(event.id))
+            .collect();
+
+        Ok(nip10::thread(events, root))
+    }
+
+    /// Send a signed event to the relays, queueing it in the outbox for those it did not reach
+    ///
+    /// Fails without publish relays rather than recording an event no relay was sent
+    async fn publish_event(
+        &self,
+        event: Event,
+        signing_path: SigningPath,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let delegator = event.tags.iter().find_map(|tag| match tag {
+            Tag::Delegation { delegator_pk, .. } => Some(*delegator_pk),
+            _ => None,
+        });
+
+        let urls: Vec<Url> = self.get_relays().into_iter().collect();
+        if urls.is_empty() {
+            return Err(NostrServiceError::NoRelays);
+        }
+        let relays = self.send_to_relays(&event, &urls).await;
+        if let Some(item) = OutboxItem::new(event.clone(), signing_path, &relays) {
+            self.update_outbox(|outbox| outbox.push(item));
+        }
+
+        let published = PublishedEvent {
+            event_id: event.id,
+            kind: event.kind,
+            created_at: event.created_at,
+            signing_path,
+            delegator,
+            relays,
+            deletion: None,
+        };
+        self.update_history(|history| history.push(published.clone()));
+
+        Ok(published)
+    }
+
+    /// Send `event` to each of `urls` on its own to collect the `OK` answers
+    ///
+    /// Relays missing from the pool, e.g. of a client built for pairing, are added first
+    async fn send_to_relays(&self, event: &Event, urls: &[Url]) -> Vec<RelayStatus> {
+        let client = self.client.lock().await.clone();
+        let mut pool = client.relays().await;
+        let missing: Vec<&Url> = urls.iter().filter(|url| !pool.contains_key(url)).collect();
+        if !missing.is_empty() {
+            for url in missing {
+                if let Err(err) = client.add_relay(url.clone()).await {
+                    warn!("Could not add relay {}: {}", url, err);
                 }
-                Err(err) => {
-                    warn!("Could not get delegation tag: {}", err);
-                    vec![]
+            }
+            client.connect().await;
+            pool = client.relays().await;
+        }
+
+        join_all(urls.iter().map(|url| {
+            let relay = pool.get(url).cloned();
+            let event = event.clone();
+            async move {
+                let result = match relay {
+                    Some(relay) => relay
+                        .send_event(event, Some(SEND_TIMEOUT))
+                        .await
+                        .map(|_| ())
+                        .map_err(|err| err.to_string()),
+                    None => Err("Relay not in the pool".to_string()),
+                };
+                RelayStatus::new(url.clone(), result)
+            }
+        }))
+        .await
+    }
+
+    /// Events waiting to be resent, oldest first
+    pub fn get_outbox(&self) -> Vec<OutboxItem> {
+        self.store().outbox()
+    }
+
+    /// Resend queued events to the relays due, returns the number of sends made
+    pub async fn process_outbox(&self) -> usize {
+        let now = Timestamp::now().as_u64();
+        let due: Vec<(Event, Vec<Url>)> = self
+            .get_outbox()
+            .into_iter()
+            .map(|item| {
+                let urls = item.due(now);
+                (item.event, urls)
+            })
+            .filter(|(_, urls)| !urls.is_empty())
+            .collect();
+        if due.is_empty() {
+            return 0;
+        }
+
+        let deliveries = join_all(due.iter().map(|(event, urls)| async move {
+            (event.id, self.send_to_relays(event, urls).await)
+        }))
+        .await;
+        let sent = deliveries.iter().map(|(_, statuses)| statuses.len()).sum();
+
+        // The outbox is read again, events may have been queued or discarded meanwhile
+        let now = Timestamp::now().as_u64();
+        self.update_outbox(|outbox| {
+            for (event_id, statuses) in &deliveries {
+                if let Some(item) = outbox.iter_mut().find(|item| item.event.id == *event_id) {
+                    item.record(statuses, now);
+                }
+            }
+            outbox.retain(|item| !item.relays.is_empty());
+        });
+        self.update_history(|history| {
+            for (event_id, statuses) in &deliveries {
+                let published = match history
+                    .iter_mut()
+                    .find(|published| published.event_id == *event_id)
+                {
+                    Some(published) => published,
+                    None => continue,
+                };
+                for status in statuses {
+                    match published
+                        .relays
+                        .iter_mut()
+                        .find(|relay| relay.relay == status.relay)
+                    {
+                        Some(relay) => *relay = status.clone(),
+                        None => published.relays.push(status.clone()),
+                    }
+                }
+            }
+        });
+
+        sent
+    }
+
+    /// Make a queued event due for resending to all its relays with a fresh backoff
+    pub fn retry_outbox(&self, event_id: &EventId) {
+        self.update_outbox(|outbox| {
+            if let Some(item) = outbox.iter_mut().find(|item| item.event.id == *event_id) {
+                item.reset();
+            }
+        });
+    }
+
+    /// Stop resending a queued event
+    pub fn discard_outbox(&self, event_id: &EventId) {
+        self.update_outbox(|outbox| outbox.retain(|item| item.event.id != *event_id));
+    }
+
+    /// Update the stored outbox, a queued event is lost if storing fails
+    fn update_outbox(&self, update: impl FnOnce(&mut Vec<OutboxItem>)) {
+        let mut outbox = self.store().outbox();
+        update(&mut outbox);
+        if let Err(err) = self.store().set_outbox(&outbox) {
+            warn!("Could not save outbox: {}", err);
+        }
+    }
+
+    /// Published events of the account, oldest first
+    pub fn get_published(&self) -> Vec<PublishedEvent> {
+        self.store().published()
+    }
+
+    /// Update the stored history, the event was published even if storing fails
+    fn update_history(&self, update: impl FnOnce(&mut Vec<PublishedEvent>)) {
+        let mut history = self.store().published();
+        update(&mut history);
+        if history.len() > HISTORY_LIMIT {
+            history.drain(..history.len() - HISTORY_LIMIT);
+        }
+        if let Err(err) = self.store().set_published(&history) {
+            warn!("Could not save published event history: {}", err);
+        }
+    }
+
+    /// Fetch the newest NIP04 direct messages sent to or from the remote signer, still encrypted
+    pub async fn get_direct_messages(&self) -> Result<Vec<DirectMessage>, NostrServiceError> {
+        let signer_pubkey = self.remote_signer.ok_or(NostrServiceError::SignerNotSet)?;
+
+        let filters = vec![
+            Filter::new()
+                .kind(Kind::EncryptedDirectMessage)
+                .author(signer_pubkey)
+                .limit(DM_PAGE_SIZE),
+            Filter::new()
+                .kind(Kind::EncryptedDirectMessage)
+                .pubkey(signer_pubkey)
+                .limit(DM_PAGE_SIZE),
+        ];
+
+        let events = self
+            .client
+            .lock()
+            .await
+            .get_events_of(filters, Some(FETCH_TIMEOUT))
+            .await?;
+
+        let mut messages = Vec::with_capacity(events.len());
+        for event in events {
+            let outgoing = event.pubkey == signer_pubkey;
+            let counterparty = if outgoing {
+                match event.tags.iter().find_map(|tag| match tag {
+                    Tag::PubKey(pubkey, _) => Some(*pubkey),
+                    _ => None,
+                }) {
+                    Some(pubkey) => pubkey,
+                    None => continue,
                 }
-                _ => vec![],
+            } else {
+                event.pubkey
             };
 
-            let event_id = client
-                .lock()
-                .await
-                .publish_text_note(content, &tag)
+            messages.push(DirectMessage {
+                event_id: event.id,
+                counterparty,
+                outgoing,
+                encrypted: event.content,
+                content: None,
+                created_at: event.created_at.as_u64(),
+            });
+        }
+
+        messages.sort_by_key(|message| message.created_at);
+
+        Ok(messages)
+    }
+
+    /// Decrypt the newest of `messages` that are still encrypted, at most [`DECRYPT_BATCH`]
+    ///
+    /// Returns the messages decrypted, those the signer fails to decrypt are left out
+    pub async fn decrypt_direct_messages(
+        &self,
+        messages: Vec<DirectMessage>,
+    ) -> Vec<DirectMessage> {
+        let mut encrypted: Vec<DirectMessage> = messages
+            .into_iter()
+            .filter(|message| message.content.is_none())
+            .collect();
+        encrypted.sort_by_key(|message| std::cmp::Reverse(message.created_at));
+        encrypted.truncate(DECRYPT_BATCH);
+
+        let mut decrypted = Vec::with_capacity(encrypted.len());
+        for mut message in encrypted {
+            match self
+                .decrypt(
+                    EncryptionScheme::Nip04,
+                    message.counterparty,
+                    &message.encrypted,
+                )
                 .await
-                .unwrap();
-            callback.emit(event_id.to_hex().into());
-        });
+            {
+                Ok(content) => {
+                    message.content = Some(content);
+                    decrypted.push(message);
+                }
+                Err(err) => warn!("Could not decrypt message {}: {}", message.event_id, err),
+            }
+        }
 
-        Ok(())
+        decrypted
+    }
+
+    /// Encrypt and send a NIP04 direct message signed by the remote signer
+    pub async fn send_direct_message(
+        &self,
+        receiver: XOnlyPublicKey,
+        text: &str,
+    ) -> Result<PublishedEvent, NostrServiceError> {
+        let signer_pubkey = self.remote_signer.ok_or(NostrServiceError::SignerNotSet)?;
+        let content = self
+            .encrypt(EncryptionScheme::Nip04, receiver, text)
+            .await?;
+
+        let unsigned_event = EventBuilder::new(
+            Kind::EncryptedDirectMessage,
+            content,
+            &[Tag::PubKey(receiver, None)],
+        )
+        .to_unsigned_event(signer_pubkey);
+        let event = self.remote_sign_event(unsigned_event).await?;
+
+        self.publish_event(event, SigningPath::RemoteSigner).await
     }
 
     /*
@@ -400,3 +1821,73 @@ impl NostrService {
     }
     */
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: Kind, created_at: u64) -> Event {
+        let mut event = EventBuilder::new(kind, "note", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        event.created_at = Timestamp::from(created_at);
+        event
+    }
+
+    fn note(created_at: u64) -> Event {
+        event(Kind::TextNote, created_at)
+    }
+
+    fn times(timeline: &Timeline) -> Vec<u64> {
+        timeline
+            .notes()
+            .iter()
+            .map(|note| note.created_at.as_u64())
+            .collect()
+    }
+
+    #[test]
+    fn timeline_orders_newest_first() {
+        let mut timeline = Timeline::new(vec![Keys::generate().public_key()]);
+
+        assert_eq!(timeline.insert(vec![note(20), note(30), note(10)]), 3);
+        assert_eq!(timeline.insert(vec![note(25)]), 1);
+
+        assert_eq!(times(&timeline), vec![30, 25, 20, 10]);
+        assert_eq!(timeline.newest(), Some(Timestamp::from(30)));
+        assert_eq!(timeline.oldest(), Some(Timestamp::from(10)));
+    }
+
+    #[test]
+    fn timeline_skips_duplicates_and_other_kinds() {
+        let mut timeline = Timeline::new(vec![Keys::generate().public_key()]);
+        let first = note(10);
+
+        assert_eq!(timeline.insert(vec![first.clone(), first.clone()]), 1);
+        assert_eq!(timeline.insert(vec![first, event(Kind::Reaction, 20)]), 0);
+        assert_eq!(times(&timeline), vec![10]);
+    }
+
+    #[test]
+    fn timeline_exhausted_by_page_without_new_notes() {
+        let mut timeline = Timeline::new(vec![Keys::generate().public_key()]);
+        let known = note(20);
+        timeline.insert(vec![known.clone()]);
+        assert!(timeline.has_more());
+
+        timeline.insert_older(vec![note(10)]);
+        assert!(timeline.has_more());
+
+        timeline.insert_older(vec![known]);
+        assert!(!timeline.has_more());
+        assert_eq!(times(&timeline), vec![20, 10]);
+    }
+
+    #[test]
+    fn timeline_without_follows_has_nothing_more() {
+        let timeline = Timeline::new(vec![]);
+
+        assert!(!timeline.has_more());
+        assert_eq!(timeline.newest(), None);
+    }
+}
@@ -0,0 +1,229 @@
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::nostr::{RelayStatus, SigningPath};
+
+/// Delay before the first resend, in seconds
+const BASE_DELAY: u64 = 5;
+
+/// Longest delay between resends, in seconds
+const MAX_DELAY: u64 = 60 * 60;
+
+/// Failed sends to a relay before it is only retried by hand
+pub const MAX_ATTEMPTS: u32 = 8;
+
+/// Delay after `attempts` failed sends, doubled on each failure
+fn backoff(attempts: u32) -> u64 {
+    BASE_DELAY
+        .saturating_mul(2u64.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_DELAY)
+}
+
+/// Relay a queued event has not reached yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingRelay {
+    pub relay: Url,
+    /// Failed sends so far
+    pub attempts: u32,
+    /// Unix time of the next automatic resend
+    pub next_attempt: u64,
+    /// Last rejection message or send error
+    pub message: String,
+}
+
+impl PendingRelay {
+    /// Relay that failed the first send at `now`
+    pub fn new(relay: Url, message: String, now: u64) -> Self {
+        Self {
+            relay,
+            attempts: 1,
+            next_attempt: now + backoff(1),
+            message,
+        }
+    }
+
+    /// Out of automatic resends
+    pub fn failed(&self) -> bool {
+        self.attempts >= MAX_ATTEMPTS
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        !self.failed() && self.next_attempt <= now
+    }
+
+    fn fail(&mut self, message: String, now: u64) {
+        self.attempts += 1;
+        self.next_attempt = now + backoff(self.attempts);
+        self.message = message;
+    }
+}
+
+/// Signed event waiting in the outbox
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxItem {
+    pub event: Event,
+    pub signing_path: SigningPath,
+    pub relays: Vec<PendingRelay>,
+}
+
+impl OutboxItem {
+    /// Queue `event` for the relays in `statuses` that did not accept it, `None` if all did
+    pub fn new(event: Event, signing_path: SigningPath, statuses: &[RelayStatus]) -> Option<Self> {
+        let now = Timestamp::now().as_u64();
+        let relays: Vec<PendingRelay> = statuses
+            .iter()
+            .filter(|status| !status.accepted)
+            .map(|status| PendingRelay::new(status.relay.clone(), status.message.clone(), now))
+            .collect();
+
+        (!relays.is_empty()).then_some(Self {
+            event,
+            signing_path,
+            relays,
+        })
+    }
+
+    /// Every relay is out of automatic resends
+    pub fn failed(&self) -> bool {
+        self.relays.iter().all(PendingRelay::failed)
+    }
+
+    /// Relays due for a resend at `now`
+    pub fn due(&self, now: u64) -> Vec<Url> {
+        self.relays
+            .iter()
+            .filter(|pending| pending.is_due(now))
+            .map(|pending| pending.relay.clone())
+            .collect()
+    }
+
+    /// Make every relay due now with a fresh backoff
+    pub fn reset(&mut self) {
+        for pending in self.relays.iter_mut() {
+            pending.attempts = 0;
+            pending.next_attempt = 0;
+        }
+    }
+
+    /// Drop the relays that accepted the event and back off the others
+    pub fn record(&mut self, statuses: &[RelayStatus], now: u64) {
+        for status in statuses {
+            if status.accepted {
+                self.relays.retain(|pending| pending.relay != status.relay);
+            } else if let Some(pending) = self
+                .relays
+                .iter_mut()
+                .find(|pending| pending.relay == status.relay)
+            {
+                pending.fail(status.message.clone(), now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(relay: &str, accepted: bool) -> RelayStatus {
+        RelayStatus {
+            relay: Url::parse(relay).unwrap(),
+            accepted,
+            message: if accepted { "" } else { "blocked" }.to_string(),
+        }
+    }
+
+    fn item(statuses: &[RelayStatus]) -> Option<OutboxItem> {
+        let event = EventBuilder::new_text_note("queued", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        OutboxItem::new(event, SigningPath::Delegation, statuses)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        assert_eq!(backoff(0), BASE_DELAY);
+        assert_eq!(backoff(1), BASE_DELAY);
+        assert_eq!(backoff(2), BASE_DELAY * 2);
+        assert_eq!(backoff(4), BASE_DELAY * 8);
+        assert_eq!(backoff(20), MAX_DELAY);
+        assert_eq!(backoff(u32::MAX), MAX_DELAY);
+    }
+
+    #[test]
+    fn queues_relays_that_did_not_accept() {
+        assert_eq!(item(&[status("wss://a.example.com", true)]), None);
+
+        let item = item(&[
+            status("wss://a.example.com", true),
+            status("wss://b.example.com", false),
+        ])
+        .unwrap();
+
+        assert_eq!(item.relays.len(), 1);
+        assert_eq!(
+            item.relays[0].relay,
+            Url::parse("wss://b.example.com").unwrap()
+        );
+        assert_eq!(item.relays[0].attempts, 1);
+        assert_eq!(item.relays[0].message, "blocked");
+    }
+
+    #[test]
+    fn record_drops_accepted_and_backs_off_failed() {
+        let mut item = item(&[
+            status("wss://a.example.com", false),
+            status("wss://b.example.com", false),
+        ])
+        .unwrap();
+
+        item.record(
+            &[
+                status("wss://a.example.com", true),
+                status("wss://b.example.com", false),
+            ],
+            1000,
+        );
+
+        assert_eq!(item.relays.len(), 1);
+        let pending = &item.relays[0];
+        assert_eq!(pending.relay, Url::parse("wss://b.example.com").unwrap());
+        assert_eq!(pending.attempts, 2);
+        assert_eq!(pending.next_attempt, 1000 + backoff(2));
+        assert!(item.due(1000).is_empty());
+        assert_eq!(item.due(1000 + backoff(2)), vec![pending.relay.clone()]);
+    }
+
+    #[test]
+    fn fails_after_max_attempts() {
+        let mut item = item(&[status("wss://a.example.com", false)]).unwrap();
+        for attempt in 2..=MAX_ATTEMPTS {
+            assert!(!item.failed());
+            item.record(&[status("wss://a.example.com", false)], attempt as u64);
+        }
+
+        assert!(item.failed());
+        assert!(item.due(u64::MAX).is_empty());
+    }
+
+    #[test]
+    fn reset_makes_every_relay_due() {
+        let mut item = item(&[status("wss://a.example.com", false)]).unwrap();
+        for now in 0..MAX_ATTEMPTS as u64 {
+            item.record(&[status("wss://a.example.com", false)], now);
+        }
+        assert!(item.failed());
+
+        item.reset();
+
+        assert!(!item.failed());
+        assert_eq!(
+            item.due(0),
+            vec![Url::parse("wss://a.example.com").unwrap()]
+        );
+        // The next failure starts the backoff over
+        item.record(&[status("wss://a.example.com", false)], 100);
+        assert_eq!(item.relays[0].attempts, 1);
+        assert_eq!(item.relays[0].next_attempt, 100 + backoff(1));
+    }
+}
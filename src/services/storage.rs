@@ -0,0 +1,243 @@
+use std::str::FromStr;
+
+use dashmap::DashSet;
+use gloo::storage::{LocalStorage, Storage};
+use log::warn;
+use nostr_sdk::Url;
+use serde::{Deserialize, Serialize};
+
+use super::error::NostrServiceError;
+use super::store::StorageBackend;
+
+/// LocalStorage key of the relay configuration
+const RELAY_CONFIG_KEY: &str = "relayConfig";
+
+/// Current version of the stored relay configuration schema
+const RELAY_CONFIG_VERSION: u32 = 1;
+
+/// Connect relay used when nothing has been saved yet
+pub const DEFAULT_CONNECT_RELAY: &str = "wss://relay.damus.io";
+
+/// Relay configuration persisted across sessions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayConfig {
+    pub version: u32,
+    pub connect_relay: String,
+    pub relays: Vec<String>,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            version: RELAY_CONFIG_VERSION,
+            connect_relay: DEFAULT_CONNECT_RELAY.to_string(),
+            relays: vec![],
+        }
+    }
+}
+
+impl RelayConfig {
+    pub fn new(connect_relay: &Url, relays: &DashSet<Url>) -> Self {
+        Self {
+            version: RELAY_CONFIG_VERSION,
+            connect_relay: connect_relay.to_string(),
+            relays: relays.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    /// Load the saved relay config of `account`, falling back to the default
+    pub fn load(account: &Account) -> Self {
+        let config = match LocalStorage::get::<String>(account.storage_key(RELAY_CONFIG_KEY)) {
+            Ok(config) => config,
+            Err(_) => return Self::default(),
+        };
+
+        match serde_json::from_str::<RelayConfig>(&config) {
+            Ok(config) if config.version == RELAY_CONFIG_VERSION => config,
+            Ok(config) => {
+                warn!("Unsupported relay config version {}", config.version);
+                Self::default()
+            }
+            Err(err) => {
+                warn!("Could not parse relay config {}", err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Save relay config of `account` to LocalStorage
+    pub fn save(&self, account: &Account) -> Result<(), NostrServiceError> {
+        LocalStorage::set(
+            account.storage_key(RELAY_CONFIG_KEY),
+            serde_json::to_string(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Remove the saved relay config of `account`
+    pub fn delete(account: &Account) {
+        LocalStorage::delete(account.storage_key(RELAY_CONFIG_KEY));
+    }
+
+    /// Connect relay as `Url`
+    pub fn connect_relay(&self) -> Url {
+        Url::from_str(&self.connect_relay)
+            .unwrap_or_else(|_| Url::from_str(DEFAULT_CONNECT_RELAY).expect("valid default"))
+    }
+
+    /// Publish relays as `Url`s, skipping any that no longer parse
+    pub fn relays(&self) -> DashSet<Url> {
+        self.relays
+            .iter()
+            .filter_map(|r| Url::from_str(r).ok())
+            .collect()
+    }
+}
+
+/// LocalStorage key of the auto renew delegation preference
+const AUTO_RENEW_KEY: &str = "autoRenewDelegation";
+
+/// Whether expiring delegations should be renewed automatically
+pub fn load_auto_renew() -> bool {
+    LocalStorage::get(AUTO_RENEW_KEY).unwrap_or(false)
+}
+
+/// Save auto renew delegation preference
+pub fn save_auto_renew(auto_renew: bool) -> Result<(), NostrServiceError> {
+    LocalStorage::set(AUTO_RENEW_KEY, auto_renew)?;
+    Ok(())
+}
+
+/// LocalStorage key of the session storage backend preference
+const STORAGE_BACKEND_KEY: &str = "storageBackend";
+
+/// Backend the session state is kept in
+pub fn load_storage_backend() -> StorageBackend {
+    LocalStorage::get(STORAGE_BACKEND_KEY).unwrap_or_default()
+}
+
+/// Save session storage backend preference
+pub fn save_storage_backend(backend: StorageBackend) -> Result<(), NostrServiceError> {
+    LocalStorage::set(STORAGE_BACKEND_KEY, backend)?;
+    Ok(())
+}
+
+/// LocalStorage key of the saved accounts
+const ACCOUNTS_KEY: &str = "accounts";
+
+/// Current version of the stored accounts schema
+const ACCOUNTS_VERSION: u32 = 1;
+
+/// Account holding data saved before there were accounts, its keys are not namespaced
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Identity with its own app key, signer, delegations and relays
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub name: String,
+}
+
+impl Account {
+    /// `key` namespaced to the account
+    pub fn storage_key(&self, key: &str) -> String {
+        if self.id == DEFAULT_ACCOUNT {
+            key.to_string()
+        } else {
+            format!("{}:{}", self.id, key)
+        }
+    }
+}
+
+/// Saved accounts and the one in use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Accounts {
+    pub version: u32,
+    pub accounts: Vec<Account>,
+    pub active: String,
+}
+
+impl Default for Accounts {
+    fn default() -> Self {
+        Self {
+            version: ACCOUNTS_VERSION,
+            accounts: vec![Account {
+                id: DEFAULT_ACCOUNT.to_string(),
+                name: "Account 1".to_string(),
+            }],
+            active: DEFAULT_ACCOUNT.to_string(),
+        }
+    }
+}
+
+impl Accounts {
+    /// Load saved accounts, falling back to the default account
+    pub fn load() -> Self {
+        let accounts = match LocalStorage::get::<String>(ACCOUNTS_KEY) {
+            Ok(accounts) => accounts,
+            Err(_) => return Self::default(),
+        };
+
+        match serde_json::from_str::<Accounts>(&accounts) {
+            Ok(accounts)
+                if accounts.version == ACCOUNTS_VERSION && !accounts.accounts.is_empty() =>
+            {
+                accounts
+            }
+            Ok(accounts) => {
+                warn!("Unsupported accounts version {}", accounts.version);
+                Self::default()
+            }
+            Err(err) => {
+                warn!("Could not parse accounts {}", err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Save accounts to LocalStorage
+    pub fn save(&self) -> Result<(), NostrServiceError> {
+        LocalStorage::set(ACCOUNTS_KEY, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Account in use, the first one if it no longer exists
+    pub fn active(&self) -> &Account {
+        self.accounts
+            .iter()
+            .find(|account| account.id == self.active)
+            .unwrap_or(&self.accounts[0])
+    }
+
+    /// Use the account with `id`, returns false if there is none
+    pub fn set_active(&mut self, id: &str) -> bool {
+        if !self.accounts.iter().any(|account| account.id == id) {
+            return false;
+        }
+        self.active = id.to_string();
+        true
+    }
+
+    /// Add an account without using it
+    pub fn create(&mut self) -> &Account {
+        let id = format!("account-{}", js_sys::Date::now() as u64);
+        self.accounts.push(Account {
+            id,
+            name: format!("Account {}", self.accounts.len() + 1),
+        });
+        &self.accounts[self.accounts.len() - 1]
+    }
+
+    /// Add an account and use it
+    pub fn add(&mut self) -> &Account {
+        self.active = self.create().id.clone();
+        self.active()
+    }
+
+    /// Remove the account with `id`, the last account is never removed
+    pub fn remove(&mut self, id: &str) {
+        if self.accounts.len() > 1 {
+            self.accounts.retain(|account| account.id != id);
+        }
+    }
+}
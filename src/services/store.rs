@@ -0,0 +1,634 @@
+use std::{cell::RefCell, collections::HashMap, fmt, marker::PhantomData, rc::Rc, str::FromStr};
+
+use futures::channel::oneshot;
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use gloo::storage::{errors::StorageError, LocalStorage, SessionStorage, Storage};
+use log::warn;
+use nostr_sdk::{secp256k1::XOnlyPublicKey, Keys, Metadata};
+use rexie::{ObjectStore, Rexie, TransactionMode};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+
+use super::error::NostrServiceError;
+use super::nip49;
+use super::nostr::{DelegationInfo, PublishedEvent};
+use super::outbox::OutboxItem;
+use super::storage::Account;
+
+/// Key of the NIP49 encrypted app key
+pub const NCRYPTSEC_KEY: &str = "ncryptsec";
+
+/// Key of the plain text app key written by earlier versions
+pub const LEGACY_PRIV_KEY: &str = "priv_key";
+
+/// Key of the remote signer pubkey
+pub const REMOTE_SIGNER_KEY: &str = "remote_pub_key";
+
+/// Key of the app delegations
+pub const DELEGATIONS_KEY: &str = "delegations";
+
+/// Key of the single delegation written by earlier versions
+pub const LEGACY_DELEGATION_KEY: &str = "delegationInfo";
+
+/// Key of the cached kind 0 metadata
+pub const METADATA_KEY: &str = "metadata";
+
+/// Key of the history of published events
+pub const PUBLISHED_KEY: &str = "published";
+
+/// Key of the signed events waiting to be resent
+pub const OUTBOX_KEY: &str = "outbox";
+
+/// Every key holding session state
+pub const SESSION_KEYS: &[&str] = &[
+    NCRYPTSEC_KEY,
+    LEGACY_PRIV_KEY,
+    REMOTE_SIGNER_KEY,
+    DELEGATIONS_KEY,
+    LEGACY_DELEGATION_KEY,
+    METADATA_KEY,
+    PUBLISHED_KEY,
+    OUTBOX_KEY,
+];
+
+/// Shared handle to the session store in use
+pub type Store = Rc<dyn SessionStore>;
+
+/// Persistence of the session state, independent of where it is kept
+pub trait SessionStore {
+    /// Stored value of `key`
+    fn get(&self, key: &str) -> Option<String>;
+
+    fn set(&self, key: &str, value: &str) -> Result<(), NostrServiceError>;
+
+    fn delete(&self, key: &str);
+
+    /// Wait until the writes made so far are persisted, fails if any of them did
+    fn flush(&self) -> LocalBoxFuture<'static, Result<(), NostrServiceError>> {
+        future::ready(Ok(())).boxed_local()
+    }
+
+    /// Remove the session state, anything else kept by the backend is left alone
+    fn clear(&self) {
+        for key in SESSION_KEYS {
+            self.delete(key);
+        }
+    }
+
+    /// Copy the session state of another store
+    fn copy_from(&self, other: &dyn SessionStore) -> Result<(), NostrServiceError> {
+        for key in SESSION_KEYS {
+            match other.get(key) {
+                Some(value) => self.set(key, &value)?,
+                None => self.delete(key),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether an encrypted app key has been saved
+    fn has_app_key(&self) -> bool {
+        self.get(NCRYPTSEC_KEY).is_some()
+    }
+
+    /// Encrypt the app key with `passphrase` and save it
+    fn save_app_key(&self, keys: &Keys, passphrase: &str) -> Result<(), NostrServiceError> {
+        let ncryptsec = nip49::encrypt(&keys.secret_key()?, passphrase, nip49::DEFAULT_LOG_N)?;
+        self.set_ncryptsec(&ncryptsec)?;
+        self.delete(LEGACY_PRIV_KEY);
+        Ok(())
+    }
+
+    /// Save an app key that is already NIP49 encrypted
+    fn set_ncryptsec(&self, ncryptsec: &str) -> Result<(), NostrServiceError> {
+        self.set(NCRYPTSEC_KEY, ncryptsec)
+    }
+
+    /// Decrypt the saved app key with `passphrase`
+    fn load_app_key(&self, passphrase: &str) -> Result<Keys, NostrServiceError> {
+        let ncryptsec = self
+            .get(NCRYPTSEC_KEY)
+            .ok_or_else(|| StorageError::KeyNotFound(NCRYPTSEC_KEY.to_string()))?;
+        let secret_key = nip49::decrypt(&ncryptsec, passphrase)?;
+        Ok(Keys::new(secret_key))
+    }
+
+    /// Plain text app key written by earlier versions
+    fn legacy_app_key(&self) -> Option<String> {
+        self.get(LEGACY_PRIV_KEY)
+    }
+
+    fn remote_signer(&self) -> Option<XOnlyPublicKey> {
+        self.get(REMOTE_SIGNER_KEY)
+            .and_then(|pubkey| XOnlyPublicKey::from_str(&pubkey).ok())
+    }
+
+    fn set_remote_signer(&self, pubkey: &XOnlyPublicKey) -> Result<(), NostrServiceError> {
+        self.set(REMOTE_SIGNER_KEY, &pubkey.to_string())
+    }
+
+    /// Stored delegations, signatures are not checked
+    fn delegations(&self) -> Result<Vec<DelegationInfo>, NostrServiceError> {
+        if let Some(delegations) = self.get(DELEGATIONS_KEY) {
+            return Ok(serde_json::from_str(&delegations)?);
+        }
+
+        // Earlier versions held a single delegation, it is moved to the list once read
+        match self.get(LEGACY_DELEGATION_KEY) {
+            Some(delegation) => {
+                let delegations = vec![serde_json::from_str(&delegation)?];
+                self.set_delegations(&delegations)?;
+                self.delete(LEGACY_DELEGATION_KEY);
+                Ok(delegations)
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    fn set_delegations(&self, delegations: &[DelegationInfo]) -> Result<(), NostrServiceError> {
+        self.set(DELEGATIONS_KEY, &serde_json::to_string(delegations)?)
+    }
+
+    fn has_stored_delegations(&self) -> bool {
+        matches!(self.delegations(), Ok(delegations) if !delegations.is_empty())
+    }
+
+    /// Cached kind 0 metadata by author
+    fn metadata(&self) -> HashMap<XOnlyPublicKey, Metadata> {
+        let metadata = match self.get(METADATA_KEY) {
+            Some(metadata) => metadata,
+            None => return HashMap::new(),
+        };
+
+        serde_json::from_str(&metadata).unwrap_or_else(|err| {
+            warn!("Could not parse cached metadata {}", err);
+            HashMap::new()
+        })
+    }
+
+    fn set_metadata(
+        &self,
+        metadata: &HashMap<XOnlyPublicKey, Metadata>,
+    ) -> Result<(), NostrServiceError> {
+        self.set(METADATA_KEY, &serde_json::to_string(metadata)?)
+    }
+
+    /// History of published events, oldest first
+    fn published(&self) -> Vec<PublishedEvent> {
+        let published = match self.get(PUBLISHED_KEY) {
+            Some(published) => published,
+            None => return vec![],
+        };
+
+        serde_json::from_str(&published).unwrap_or_else(|err| {
+            warn!("Could not parse published event history {}", err);
+            vec![]
+        })
+    }
+
+    fn set_published(&self, published: &[PublishedEvent]) -> Result<(), NostrServiceError> {
+        self.set(PUBLISHED_KEY, &serde_json::to_string(published)?)
+    }
+
+    /// Events queued for relays they did not reach, oldest first
+    fn outbox(&self) -> Vec<OutboxItem> {
+        let outbox = match self.get(OUTBOX_KEY) {
+            Some(outbox) => outbox,
+            None => return vec![],
+        };
+
+        serde_json::from_str(&outbox).unwrap_or_else(|err| {
+            warn!("Could not parse outbox {}", err);
+            vec![]
+        })
+    }
+
+    fn set_outbox(&self, outbox: &[OutboxItem]) -> Result<(), NostrServiceError> {
+        self.set(OUTBOX_KEY, &serde_json::to_string(outbox)?)
+    }
+}
+
+/// Store with keys namespaced to an account
+pub struct NamespacedStore {
+    inner: Store,
+    account: Account,
+}
+
+impl NamespacedStore {
+    pub fn new(inner: Store, account: &Account) -> Self {
+        Self {
+            inner,
+            account: account.clone(),
+        }
+    }
+}
+
+impl SessionStore for NamespacedStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.inner.get(&self.account.storage_key(key))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), NostrServiceError> {
+        self.inner.set(&self.account.storage_key(key), value)
+    }
+
+    fn delete(&self, key: &str) {
+        self.inner.delete(&self.account.storage_key(key));
+    }
+
+    fn flush(&self) -> LocalBoxFuture<'static, Result<(), NostrServiceError>> {
+        self.inner.flush()
+    }
+}
+
+/// Browser web storage, `SessionStorage` or `LocalStorage`
+pub struct WebStore<S>(PhantomData<S>);
+
+impl<S> Default for WebStore<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<S: Storage> SessionStore for WebStore<S> {
+    fn get(&self, key: &str) -> Option<String> {
+        S::get(key).ok()
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), NostrServiceError> {
+        S::set(key, value)?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) {
+        S::delete(key);
+    }
+}
+
+/// Store kept in memory only, forgotten on reload
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: RefCell<HashMap<String, String>>,
+}
+
+impl SessionStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.entries.borrow().get(key).cloned()
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), NostrServiceError> {
+        self.entries
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) {
+        self.entries.borrow_mut().remove(key);
+    }
+}
+
+/// IndexedDB database of the app
+const DATABASE: &str = "nostr-connect";
+
+/// Object store holding the session state
+const OBJECT_STORE: &str = "session";
+
+/// IndexedDB backed store
+///
+/// Values are loaded when opened and served from memory, writes go to
+/// the database in the background until [`SessionStore::flush`] waits for them
+pub struct IndexedDbStore {
+    db: Rc<Rexie>,
+    cache: MemoryStore,
+    /// Completion of the writes not flushed yet
+    pending: RefCell<Vec<oneshot::Receiver<()>>>,
+    /// First write error since the last flush
+    failed: Rc<RefCell<Option<rexie::Error>>>,
+}
+
+impl IndexedDbStore {
+    /// Open the database and load the stored values
+    pub async fn open() -> Result<Self, NostrServiceError> {
+        let db = Rexie::builder(DATABASE)
+            .version(1)
+            .add_object_store(ObjectStore::new(OBJECT_STORE))
+            .build()
+            .await?;
+
+        let cache = MemoryStore::default();
+        let transaction = db.transaction(&[OBJECT_STORE], TransactionMode::ReadOnly)?;
+        let store = transaction.store(OBJECT_STORE)?;
+        for (key, value) in store.get_all(None, None, None, None).await? {
+            if let (Some(key), Some(value)) = (key.as_string(), value.as_string()) {
+                cache.set(&key, &value)?;
+            }
+        }
+        transaction.done().await?;
+
+        Ok(Self {
+            db: Rc::new(db),
+            cache,
+            pending: RefCell::new(vec![]),
+            failed: Rc::new(RefCell::new(None)),
+        })
+    }
+
+    /// Write `value` to the database, deleting the key if `None`
+    fn write(&self, key: &str, value: Option<&str>) {
+        let db = self.db.clone();
+        let failed = self.failed.clone();
+        let key = JsValue::from_str(key);
+        let value = value.map(JsValue::from_str);
+        let (done, written) = oneshot::channel();
+        spawn_local(async move {
+            if let Err(err) = write_entry(&db, &key, value.as_ref()).await {
+                warn!("Could not write to IndexedDB: {}", err);
+                failed.borrow_mut().get_or_insert(err);
+            }
+            let _ = done.send(());
+        });
+
+        let mut pending = self.pending.borrow_mut();
+        // Finished writes only matter through `failed`
+        pending.retain_mut(|written| matches!(written.try_recv(), Ok(None)));
+        pending.push(written);
+    }
+}
+
+async fn write_entry(db: &Rexie, key: &JsValue, value: Option<&JsValue>) -> rexie::Result<()> {
+    let transaction = db.transaction(&[OBJECT_STORE], TransactionMode::ReadWrite)?;
+    let store = transaction.store(OBJECT_STORE)?;
+    match value {
+        Some(value) => {
+            store.put(value, Some(key)).await?;
+        }
+        None => store.delete(key).await?,
+    }
+    transaction.done().await?;
+    Ok(())
+}
+
+impl SessionStore for IndexedDbStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.cache.get(key)
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), NostrServiceError> {
+        self.cache.set(key, value)?;
+        self.write(key, Some(value));
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) {
+        self.cache.delete(key);
+        self.write(key, None);
+    }
+
+    fn flush(&self) -> LocalBoxFuture<'static, Result<(), NostrServiceError>> {
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        let failed = self.failed.clone();
+        async move {
+            future::join_all(pending).await;
+            match failed.borrow_mut().take() {
+                Some(err) => Err(err.into()),
+                None => Ok(()),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Where the session state is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StorageBackend {
+    /// Cleared when the tab is closed
+    #[default]
+    Session,
+    /// Kept until logging out
+    Local,
+    IndexedDb,
+    /// Forgotten on reload
+    Memory,
+}
+
+impl StorageBackend {
+    pub const ALL: [Self; 4] = [Self::Session, Self::Local, Self::IndexedDb, Self::Memory];
+
+    /// Identifier used in select inputs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::Local => "local",
+            Self::IndexedDb => "indexeddb",
+            Self::Memory => "memory",
+        }
+    }
+
+    /// Open a store on this backend
+    pub async fn open(self) -> Result<Store, NostrServiceError> {
+        Ok(match self {
+            Self::Session => Rc::new(WebStore::<SessionStorage>::default()),
+            Self::Local => Rc::new(WebStore::<LocalStorage>::default()),
+            Self::IndexedDb => Rc::new(IndexedDbStore::open().await?),
+            Self::Memory => Rc::new(MemoryStore::default()),
+        })
+    }
+}
+
+impl fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Session => write!(f, "Session storage (cleared when the tab closes)"),
+            Self::Local => write!(f, "Local storage"),
+            Self::IndexedDb => write!(f, "IndexedDB"),
+            Self::Memory => write!(f, "Memory (forgotten on reload)"),
+        }
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = NostrServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.as_str() == s)
+            .ok_or_else(|| NostrServiceError::UnknownBackend(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_sdk::nips::nip26::{sign_delegation, Conditions};
+    use nostr_sdk::{EventBuilder, Kind, Timestamp, Url};
+
+    use super::*;
+    use crate::services::nostr::{RelayStatus, SigningPath};
+    use crate::services::storage::DEFAULT_ACCOUNT;
+
+    fn account(id: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            name: id.to_string(),
+        }
+    }
+
+    fn delegation() -> DelegationInfo {
+        let delegator = Keys::generate();
+        let conditions = Conditions::from_str("kind=1&created_at<4000000000").unwrap();
+        let signature = sign_delegation(
+            &delegator,
+            Keys::generate().public_key(),
+            conditions.clone(),
+        )
+        .unwrap();
+        DelegationInfo {
+            delegator_pubkey: delegator.public_key(),
+            conditions,
+            signature,
+        }
+    }
+
+    fn rejected(relay: &str) -> RelayStatus {
+        RelayStatus {
+            relay: Url::parse(relay).unwrap(),
+            accepted: false,
+            message: "blocked".to_string(),
+        }
+    }
+
+    #[test]
+    fn copies_and_clears_session_state() {
+        let from = MemoryStore::default();
+        from.set(NCRYPTSEC_KEY, "ncryptsec1").unwrap();
+        from.set(OUTBOX_KEY, "[]").unwrap();
+        from.set("unrelated", "kept").unwrap();
+
+        let to = MemoryStore::default();
+        to.set(REMOTE_SIGNER_KEY, "stale").unwrap();
+        to.copy_from(&from).unwrap();
+
+        assert_eq!(to.get(NCRYPTSEC_KEY).as_deref(), Some("ncryptsec1"));
+        assert_eq!(to.get(OUTBOX_KEY).as_deref(), Some("[]"));
+        assert_eq!(to.get(REMOTE_SIGNER_KEY), None);
+        assert_eq!(to.get("unrelated"), None);
+
+        from.clear();
+        assert_eq!(from.get(NCRYPTSEC_KEY), None);
+        assert_eq!(from.get(OUTBOX_KEY), None);
+        assert_eq!(from.get("unrelated").as_deref(), Some("kept"));
+    }
+
+    #[test]
+    fn namespaces_keys_by_account() {
+        let inner: Store = Rc::new(MemoryStore::default());
+        let default = NamespacedStore::new(inner.clone(), &account(DEFAULT_ACCOUNT));
+        let other = NamespacedStore::new(inner.clone(), &account("account-1"));
+
+        default.set(NCRYPTSEC_KEY, "default").unwrap();
+        other.set(NCRYPTSEC_KEY, "other").unwrap();
+
+        assert_eq!(inner.get(NCRYPTSEC_KEY).as_deref(), Some("default"));
+        assert_eq!(inner.get("account-1:ncryptsec").as_deref(), Some("other"));
+        assert_eq!(other.get(NCRYPTSEC_KEY).as_deref(), Some("other"));
+
+        other.clear();
+        assert_eq!(other.get(NCRYPTSEC_KEY), None);
+        assert_eq!(default.get(NCRYPTSEC_KEY).as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn round_trips_delegations() {
+        let store = MemoryStore::default();
+        assert!(!store.has_stored_delegations());
+
+        let delegations = vec![delegation(), delegation()];
+        store.set_delegations(&delegations).unwrap();
+
+        assert!(store.has_stored_delegations());
+        assert_eq!(
+            serde_json::to_string(&store.delegations().unwrap()).unwrap(),
+            serde_json::to_string(&delegations).unwrap()
+        );
+    }
+
+    #[test]
+    fn migrates_legacy_delegation() {
+        let store = MemoryStore::default();
+        let legacy = delegation();
+        store
+            .set(
+                LEGACY_DELEGATION_KEY,
+                &serde_json::to_string(&legacy).unwrap(),
+            )
+            .unwrap();
+
+        let delegations = store.delegations().unwrap();
+
+        assert_eq!(delegations.len(), 1);
+        assert_eq!(delegations[0].signature, legacy.signature);
+        assert_eq!(store.get(LEGACY_DELEGATION_KEY), None);
+        assert!(store.get(DELEGATIONS_KEY).is_some());
+    }
+
+    #[test]
+    fn ignores_empty_delegation_list() {
+        let store = MemoryStore::default();
+        store.set_delegations(&[]).unwrap();
+
+        assert!(!store.has_stored_delegations());
+    }
+
+    #[test]
+    fn round_trips_outbox() {
+        let store = MemoryStore::default();
+        assert!(store.outbox().is_empty());
+
+        let event = EventBuilder::new_text_note("queued", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        let item = OutboxItem::new(
+            event,
+            SigningPath::Delegation,
+            &[rejected("wss://relay.example.com")],
+        )
+        .unwrap();
+        store.set_outbox(&[item.clone()]).unwrap();
+
+        assert_eq!(store.outbox(), vec![item]);
+    }
+
+    #[test]
+    fn round_trips_published() {
+        let store = MemoryStore::default();
+        assert!(store.published().is_empty());
+
+        let event = EventBuilder::new_text_note("published", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        let published = PublishedEvent {
+            event_id: event.id,
+            kind: Kind::TextNote,
+            created_at: Timestamp::from(1_700_000_000),
+            signing_path: SigningPath::RemoteSigner,
+            delegator: None,
+            relays: vec![rejected("wss://relay.example.com")],
+            deletion: None,
+        };
+        store.set_published(&[published.clone()]).unwrap();
+
+        assert_eq!(store.published(), vec![published]);
+    }
+
+    #[test]
+    fn ignores_unparsable_values() {
+        let store = MemoryStore::default();
+        store.set(OUTBOX_KEY, "not json").unwrap();
+        store.set(PUBLISHED_KEY, "not json").unwrap();
+
+        assert!(store.outbox().is_empty());
+        assert!(store.published().is_empty());
+    }
+}
@@ -1,4 +1,7 @@
+use std::str::FromStr;
+
 use nostr_sdk::prelude::*;
+use nostr_sdk::secp256k1::XOnlyPublicKey;
 
 use log::debug;
 
@@ -23,3 +26,33 @@ pub fn handle_keys(private_key: Option<String>, hex: bool) -> Result<Keys> {
     }
     Ok(keys)
 }
+
+/// Parse a pubkey from npub or hex
+pub fn parse_pubkey(pubkey: &str) -> Option<XOnlyPublicKey> {
+    let pubkey = pubkey.trim();
+    XOnlyPublicKey::from_bech32(pubkey)
+        .ok()
+        .or_else(|| XOnlyPublicKey::from_str(pubkey).ok())
+}
+
+/// Parse a `datetime-local` input value in local time to unix time
+pub fn from_datetime_local(value: &str) -> Option<u64> {
+    if value.is_empty() {
+        return None;
+    }
+
+    // Dates with a time and no offset are read in the local time zone
+    let millis = js_sys::Date::new(&wasm_bindgen::JsValue::from_str(value)).get_time();
+    if millis.is_nan() || millis < 0.0 {
+        return None;
+    }
+
+    Some((millis / 1000.0) as u64)
+}
+
+/// Format a unix timestamp in the browser locale
+pub fn format_timestamp(timestamp: u64) -> String {
+    js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(timestamp as f64 * 1000.0))
+        .to_locale_string("default", &wasm_bindgen::JsValue::UNDEFINED)
+        .into()
+}
@@ -5,6 +5,7 @@ use nostr_sdk::secp256k1::XOnlyPublicKey;
 use nostr_sdk::url::Url;
 use qrcode::render::svg;
 use qrcode::QrCode;
+use web_sys::HtmlInputElement;
 use yew::prelude::*;
 use yew::virtual_dom::VNode;
 
@@ -13,11 +14,10 @@ enum State {
     Connect,
 }
 
-/*
 pub enum Msg {
-    CopyConnect,
+    // CopyConnect,
+    AddSigner,
 }
-*/
 
 #[derive(Properties, PartialEq, Default, Clone)]
 pub struct Props {
@@ -29,6 +29,8 @@ pub struct Props {
     pub name: AttrValue,
     pub connected_cb: Callback<AttrValue>,
     pub set_relay_cb: Callback<AttrValue>,
+    /// Signer pubkey or NIP05 identifier entered by hand
+    pub add_signer_cb: Callback<AttrValue>,
 }
 
 #[derive(Debug)]
@@ -36,9 +38,10 @@ pub struct Connect {
     state: State,
     connect_string: Option<String>,
     connect_qr: Option<VNode>,
+    signer_input: NodeRef,
 }
 impl Component for Connect {
-    type Message = ();
+    type Message = Msg;
     type Properties = Props;
 
     fn create(ctx: &Context<Self>) -> Self {
@@ -62,16 +65,26 @@ impl Component for Connect {
             state: State::Connect,
             connect_string: Some(connect_uri.to_string()),
             connect_qr: Some(connect_svg),
+            signer_input: NodeRef::default(),
         }
     }
 
-    /*
-        fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
-            match msg {
-                Msg::CopyConnect => false,
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            // Msg::CopyConnect => false,
+            Msg::AddSigner => {
+                if let Some(input) = self.signer_input.cast::<HtmlInputElement>() {
+                    let signer = input.value().trim().to_string();
+                    if !signer.is_empty() {
+                        ctx.props().add_signer_cb.emit(signer.into());
+                        input.set_value("");
+                    }
+                }
+                false
             }
         }
-    */
+    }
+
     fn view(&self, ctx: &Context<Self>) -> Html {
         match self.state {
             State::Connect => self.connect_info(ctx),
@@ -79,7 +92,7 @@ impl Component for Connect {
     }
 }
 impl Connect {
-    fn connect_info(&self, _ctx: &Context<Self>) -> Html {
+    fn connect_info(&self, ctx: &Context<Self>) -> Html {
         html! {
         <>
         <div class="flex justify-center">
@@ -92,6 +105,11 @@ impl Connect {
                  // TODO: should be a button to copy
                  // <button type="button" class="text-white absolute right-2.5 bottom-2.5 bg-blue-700 hover:bg-blue-800 focus:ring-4 focus:outline-none focus:ring-blue-300 font-medium rounded-lg text-sm px-4 py-2 dark:bg-blue-600 dark:hover:bg-blue-700 dark:focus:ring-blue-800" onclick={ctx.link().callback(|_| Msg::CopyConnect)}>{"Copy"}</button>
               </div>
+              <div class="mt-4">
+                 <label for="signer" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Signer pubkey or NIP-05" }</label>
+                 <input type="text" id="signer" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 mb-2 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="npub... or name@domain" ref={self.signer_input.clone()}/>
+                 <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::AddSigner)}>{ "Use signer" }</button>
+              </div>
            </div>
         </div>
         </>
@@ -0,0 +1,195 @@
+use nostr_sdk::Metadata;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+/// Editable kind 0 fields, empty when unset
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ProfileFields {
+    pub name: AttrValue,
+    pub display_name: AttrValue,
+    pub about: AttrValue,
+    pub picture: AttrValue,
+    pub banner: AttrValue,
+    pub nip05: AttrValue,
+    pub lud16: AttrValue,
+}
+
+impl From<&Metadata> for ProfileFields {
+    fn from(metadata: &Metadata) -> Self {
+        let field = |value: &Option<String>| AttrValue::from(value.clone().unwrap_or_default());
+
+        Self {
+            name: field(&metadata.name),
+            display_name: field(&metadata.display_name),
+            about: field(&metadata.about),
+            picture: field(&metadata.picture),
+            banner: field(&metadata.banner),
+            nip05: field(&metadata.nip05),
+            lud16: field(&metadata.lud16),
+        }
+    }
+}
+
+impl ProfileFields {
+    /// Set the edited fields on `metadata`, fields the editor does not show are kept
+    pub fn apply(&self, mut metadata: Metadata) -> Metadata {
+        let value = |field: &AttrValue| {
+            let field = field.trim();
+            (!field.is_empty()).then(|| field.to_string())
+        };
+
+        metadata.name = value(&self.name);
+        metadata.display_name = value(&self.display_name);
+        metadata.about = value(&self.about);
+        metadata.picture = value(&self.picture);
+        metadata.banner = value(&self.banner);
+        metadata.nip05 = value(&self.nip05);
+        metadata.lud16 = value(&self.lud16);
+        metadata
+    }
+}
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    /// Current profile, `None` while it is loaded
+    pub profile: Option<ProfileFields>,
+    /// Relays sent the current profile, otherwise publishing needs confirming
+    pub loaded: bool,
+    /// Metadata is being published
+    pub saving: bool,
+    pub save_cb: Callback<ProfileFields>,
+    pub reload_cb: Callback<MouseEvent>,
+}
+
+pub enum Msg {
+    Save,
+    /// Confirm there is no profile to overwrite
+    Confirm(bool),
+}
+
+pub struct EditProfile {
+    /// Publishing a profile that could not be loaded was confirmed
+    confirmed: bool,
+    name: NodeRef,
+    display_name: NodeRef,
+    about: NodeRef,
+    picture: NodeRef,
+    banner: NodeRef,
+    nip05: NodeRef,
+    lud16: NodeRef,
+}
+
+impl Component for EditProfile {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            confirmed: false,
+            name: NodeRef::default(),
+            display_name: NodeRef::default(),
+            about: NodeRef::default(),
+            picture: NodeRef::default(),
+            banner: NodeRef::default(),
+            nip05: NodeRef::default(),
+            lud16: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Save if !ctx.props().loaded && !self.confirmed => false,
+            Msg::Save => {
+                let input = |node_ref: &NodeRef| {
+                    node_ref
+                        .cast::<HtmlInputElement>()
+                        .map(|input| AttrValue::from(input.value()))
+                        .unwrap_or_default()
+                };
+                let about = self
+                    .about
+                    .cast::<HtmlTextAreaElement>()
+                    .map(|input| AttrValue::from(input.value()))
+                    .unwrap_or_default();
+
+                ctx.props().save_cb.emit(ProfileFields {
+                    name: input(&self.name),
+                    display_name: input(&self.display_name),
+                    about,
+                    picture: input(&self.picture),
+                    banner: input(&self.banner),
+                    nip05: input(&self.nip05),
+                    lud16: input(&self.lud16),
+                });
+                false
+            }
+            Msg::Confirm(confirmed) => {
+                self.confirmed = confirmed;
+                true
+            }
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        // Confirmation holds for the profile it was given for only
+        if ctx.props().profile != old_props.profile || ctx.props().loaded != old_props.loaded {
+            self.confirmed = false;
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let profile = match &ctx.props().profile {
+            Some(profile) => profile,
+            None => {
+                return html! {
+                    <p class="p-4 text-base text-gray-500 dark:text-gray-400">{ "Loading profile..." }</p>
+                }
+            }
+        };
+
+        let can_save = !ctx.props().saving && (ctx.props().loaded || self.confirmed);
+        let confirm = ctx.link().callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::Confirm(input.checked())
+        });
+
+        html! {
+            <div class="p-4 mb-20">
+            <h2 class="text-4xl font-extrabold dark:text-white">{ "Profile" }</h2>
+            if !ctx.props().loaded {
+                <div class="p-4 mb-4 text-sm text-yellow-800 rounded-lg bg-yellow-50 dark:bg-gray-800 dark:text-yellow-300" role="alert">
+                    { "No relay sent the current profile. Publishing replaces any profile that exists with the fields below, reload to try again." }
+                    <div class="flex items-center mt-2">
+                        <input id="profile-confirm" type="checkbox" class="w-4 h-4 text-purple-600 bg-gray-100 border-gray-300 rounded focus:ring-purple-500 dark:focus:ring-purple-600 dark:ring-offset-gray-800 dark:bg-gray-700 dark:border-gray-600" checked={self.confirmed} onchange={confirm}/>
+                        <label for="profile-confirm" class="ml-2 font-medium">{ "There is no profile yet, publish anyway" }</label>
+                    </div>
+                </div>
+            }
+            { Self::field("profile-name", "Name", &self.name, &profile.name) }
+            { Self::field("profile-display-name", "Display name", &self.display_name, &profile.display_name) }
+            <label for="profile-about" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "About" }</label>
+            <textarea id="profile-about" rows="4" class="block p-2.5 mb-2 w-full text-sm text-gray-900 bg-gray-50 rounded-lg border border-gray-300 focus:ring-blue-500 focus:border-blue-500 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" value={profile.about.clone()} ref={self.about.clone()}></textarea>
+            { Self::field("profile-picture", "Picture URL", &self.picture, &profile.picture) }
+            { Self::field("profile-banner", "Banner URL", &self.banner, &profile.banner) }
+            { Self::field("profile-nip05", "NIP-05 identifier", &self.nip05, &profile.nip05) }
+            { Self::field("profile-lud16", "Lightning address", &self.lud16, &profile.lud16) }
+            <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 mr-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" disabled={!can_save} onclick={ctx.link().callback(|_| Msg::Save)}>
+                { if ctx.props().saving { "Publishing..." } else { "Publish profile" } }
+            </button>
+            <button type="button" class="text-purple-700 border border-purple-700 hover:bg-purple-700 hover:text-white focus:ring-4 focus:outline-none focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 text-center dark:border-purple-500 dark:text-purple-500 dark:hover:text-white dark:focus:ring-purple-800 dark:hover:bg-purple-500" onclick={ctx.props().reload_cb.clone()}>{ "Reload" }</button>
+            </div>
+        }
+    }
+}
+
+impl EditProfile {
+    fn field(id: &'static str, label: &'static str, node_ref: &NodeRef, value: &AttrValue) -> Html {
+        html! {
+            <>
+            <label for={id} class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ label }</label>
+            <input type="text" id={id} class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 mb-2 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" value={value.clone()} ref={node_ref.clone()}/>
+            </>
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use yew::prelude::*;
+
+use crate::components::outbox::{OutboxList, OutboxProp};
+use crate::components::published::{PublishedList, PublishedProp};
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    /// Newest first
+    pub published: Vec<PublishedProp>,
+    /// Event id
+    pub delete_cb: Callback<AttrValue>,
+    /// Oldest first
+    pub outbox: Vec<OutboxProp>,
+    /// Event id
+    pub retry_cb: Callback<AttrValue>,
+    /// Event id
+    pub discard_cb: Callback<AttrValue>,
+}
+
+pub struct History;
+
+impl Component for History {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+
+        html! {
+            <div class="p-4 mb-20">
+            if !props.outbox.is_empty() {
+                <h2 class="text-4xl font-extrabold dark:text-white">{ "Outbox" }</h2>
+                <p class="text-base text-gray-500 dark:text-gray-400">{ "Events not accepted by every relay yet" }</p>
+                <OutboxList outbox={props.outbox.clone()} retry_cb={props.retry_cb.clone()} discard_cb={props.discard_cb.clone()} />
+            }
+            <h2 class="text-4xl font-extrabold dark:text-white">{ "Published events" }</h2>
+            if props.published.is_empty() {
+                <p class="text-base text-gray-500 dark:text-gray-400">{ "Nothing published yet" }</p>
+            }
+            <PublishedList published={props.published.clone()} delete_cb={props.delete_cb.clone()} />
+            </div>
+        }
+    }
+}
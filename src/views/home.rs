@@ -2,6 +2,11 @@ use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
 use super::settings::DelegationInfoProp;
+use crate::components::note::{Note, NoteProp};
+use crate::components::profile::{Profile, ProfileProp};
+use crate::services::interactions::Reaction;
+use crate::services::nostr::ScheduledNote;
+use crate::utils::{format_timestamp, from_datetime_local};
 
 enum State {
     // NotConnected,
@@ -14,12 +19,61 @@ pub enum Msg {
 pub struct Home {
     state: State,
     note_text: NodeRef,
+    publish_at: NodeRef,
 }
+
+/// Note waiting for its publish time ready to render
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ScheduledProp {
+    pub id: u64,
+    pub content: AttrValue,
+    /// Publish time in the browser locale
+    pub publish_at: AttrValue,
+    /// Error of the failed publish attempt
+    pub failed: Option<AttrValue>,
+}
+
+impl From<&ScheduledNote> for ScheduledProp {
+    fn from(note: &ScheduledNote) -> Self {
+        Self {
+            id: note.id,
+            content: note.content.clone().into(),
+            publish_at: format_timestamp(note.publish_at.as_u64()).into(),
+            failed: note.failed.clone().map(AttrValue::from),
+        }
+    }
+}
+
 #[derive(Properties, PartialEq, Default, Clone)]
 pub struct Props {
     pub note_cb: Callback<AttrValue>,
-    pub delegator: Option<DelegationInfoProp>,
-    pub remote_signer: Option<AttrValue>,
+    /// Note text and unix time to publish it at
+    pub schedule_cb: Callback<(AttrValue, u64)>,
+    /// Earliest publish time first
+    pub scheduled: Vec<ScheduledProp>,
+    /// Scheduled note id
+    pub cancel_scheduled_cb: Callback<u64>,
+    /// Failed scheduled note id
+    pub retry_scheduled_cb: Callback<u64>,
+    pub delegations: Vec<DelegationInfoProp>,
+    pub remote_signer: Option<ProfileProp>,
+    /// Notes of followed pubkeys, newest first
+    pub notes: Vec<NoteProp>,
+    /// Timeline is being fetched
+    pub loading_notes: bool,
+    /// Older notes may be available
+    pub has_more_notes: bool,
+    pub more_notes_cb: Callback<MouseEvent>,
+    /// Note id and reply text
+    pub reply_cb: Callback<(AttrValue, AttrValue)>,
+    /// Note id and quoting text
+    pub quote_cb: Callback<(AttrValue, AttrValue)>,
+    /// Note id
+    pub thread_cb: Callback<AttrValue>,
+    /// Note id and reaction
+    pub react_cb: Callback<(AttrValue, Reaction)>,
+    /// Note id
+    pub repost_cb: Callback<AttrValue>,
 }
 
 impl Component for Home {
@@ -30,6 +84,7 @@ impl Component for Home {
         Self {
             state: State::Connected,
             note_text: NodeRef::default(),
+            publish_at: NodeRef::default(),
         }
     }
 
@@ -64,13 +119,21 @@ impl Home {
 
     fn connected(&self, ctx: &Context<Self>) -> Html {
         let h = self.note_text.cast::<HtmlInputElement>();
+        let publish_at = self.publish_at.cast::<HtmlInputElement>();
         let cb = ctx.props().note_cb.clone();
+        let schedule_cb = ctx.props().schedule_cb.clone();
         let onsubmit = ctx.link().callback(move |e: SubmitEvent| {
             e.prevent_default();
             if let Some(input) = &h {
                 let value = input.value();
-                // do something with value
-                cb.emit(value.into());
+                // Publish now unless a time is set
+                match publish_at
+                    .as_ref()
+                    .and_then(|input| from_datetime_local(&input.value()))
+                {
+                    Some(time) => schedule_cb.emit((value.into(), time)),
+                    None => cb.emit(value.into()),
+                }
             }
 
             Msg::SubmitNote("".to_string())
@@ -81,9 +144,40 @@ impl Home {
             <form {onsubmit}>
                 <label for="message" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Text note" }</label>
                 <textarea id="message" rows="4" class="block p-2.5 w-full text-sm text-gray-900 bg-gray-50 rounded-lg border border-gray-300 focus:ring-blue-500 focus:border-blue-500 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="Write your thoughts here..." ref={self.note_text.clone()}></textarea>
-                <br/>
+                <label for="publish_at" class="block mt-2 mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Publish at (optional, while this tab is open)" }</label>
+                <input type="datetime-local" id="publish_at" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block p-2.5 mb-2 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.publish_at.clone()}/>
                 <button type="submit" value="submit" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900">{ "Publish" }</button>
             </form>
+            if !ctx.props().scheduled.is_empty() {
+                <h3 class="text-xl font-bold dark:text-white">{ "Scheduled notes" }</h3>
+                <ul class="max-w-md divide-y divide-gray-200 dark:divide-gray-700">
+                    {
+                        ctx.props().scheduled.iter().map(|note| {
+                            let id = note.id;
+                            let cancel_cb = ctx.props().cancel_scheduled_cb.clone();
+                            let onclick = Callback::from(move |_| cancel_cb.emit(id));
+                            let retry_cb = ctx.props().retry_scheduled_cb.clone();
+                            let retry = Callback::from(move |_| retry_cb.emit(id));
+                            html! {
+                                <li class="py-3" key={note.id}>
+                                    if let Some(failed) = &note.failed {
+                                        <p class="text-xs text-red-700 dark:text-red-400">{ format!("Could not publish at {}: {}", note.publish_at, failed) }</p>
+                                    } else {
+                                        <p class="text-xs text-gray-500 dark:text-gray-400">{ format!("Publishing at {}", note.publish_at) }</p>
+                                    }
+                                    <p class="text-base text-gray-900 whitespace-pre-wrap break-words dark:text-white">{ note.content.clone() }</p>
+                                    <div class="flex space-x-4 text-sm font-medium">
+                                        if note.failed.is_some() {
+                                            <button type="button" class="text-purple-700 hover:underline dark:text-purple-400" onclick={retry}>{ "Retry" }</button>
+                                        }
+                                        <button type="button" class="text-red-700 hover:underline dark:text-red-500" {onclick}>{ "Cancel" }</button>
+                                    </div>
+                                </li>
+                            }
+                        }).collect::<Html>()
+                    }
+                </ul>
+            }
 
 
             // TODO: Show what key events are being sent with
@@ -91,20 +185,20 @@ impl Home {
             // Option 1: using the remote signer
             // Show pubkey of signer
             {
-            if let Some(delegator) = &ctx.props().delegator {
-                html! {
+            if !ctx.props().delegations.is_empty() {
+                ctx.props().delegations.iter().map(|delegator| html! {
                     <>
-                    <p class="text-4xl text-gray-900 font-extralight dark:text-white">{ format!("Publishing events with delegation from: {}", delegator.delegator_pubkey) }</p>
+                    <p class="text-4xl text-gray-900 font-extralight dark:text-white">{ "Publishing events with delegation from:" }</p>
+                    <Profile profile={delegator.profile.clone()} />
                     <p class="text-4xl text-gray-900 font-extralight dark:text-white">{ format!("Delegated from {} to {}", delegator.valid_from, delegator.valid_to) }</p>
                     <p class="text-4xl text-gray-900 font-extralight dark:text-white">{ format!("Valid for kinds: {}", delegator.kinds) }</p>
                     </>
-
-
-                }
+                }).collect::<Html>()
             } else if let Some(remote_signer) = &ctx.props().remote_signer {
                 html! {
                     <>
-                    <p class="text-4xl text-gray-900 font-extralight dark:text-white">{ format!("Publishing events with remote signer {}", remote_signer) }</p>
+                    <p class="text-4xl text-gray-900 font-extralight dark:text-white">{ "Publishing events with remote signer" }</p>
+                    <Profile profile={remote_signer.clone()} />
                     </>
                 }
             } else {
@@ -117,6 +211,27 @@ impl Home {
 
             // Option 2: Delegation
             // Show delegator and conditions
+
+            // Timeline
+            <h2 class="mt-6 text-4xl font-extrabold dark:text-white">{ "Timeline" }</h2>
+            <ul class="mb-20 max-w-md divide-y divide-gray-200 dark:divide-gray-700">
+                {
+                    ctx.props().notes.iter().map(|note| html! {
+                        <li key={note.id.as_str()}>
+                            <Note note={note.clone()} reply_cb={ctx.props().reply_cb.clone()} quote_cb={ctx.props().quote_cb.clone()} thread_cb={ctx.props().thread_cb.clone()} react_cb={ctx.props().react_cb.clone()} repost_cb={ctx.props().repost_cb.clone()} />
+                        </li>
+                    }).collect::<Html>()
+                }
+                if ctx.props().loading_notes {
+                    <li class="py-3 text-base text-gray-500 dark:text-gray-400">{ "Loading notes..." }</li>
+                } else if ctx.props().notes.is_empty() {
+                    <li class="py-3 text-base text-gray-500 dark:text-gray-400">{ "No notes from followed pubkeys" }</li>
+                } else if ctx.props().has_more_notes {
+                    <li class="py-3">
+                        <button type="button" class="text-purple-700 border border-purple-700 hover:bg-purple-700 hover:text-white focus:ring-4 focus:outline-none focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 text-center dark:border-purple-500 dark:text-purple-500 dark:hover:text-white dark:focus:ring-purple-800 dark:hover:bg-purple-500" onclick={ctx.props().more_notes_cb.clone()}>{ "Load older notes" }</button>
+                    </li>
+                }
+            </ul>
             </>
 
         }
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use nostr_sdk::{nips::nip19::ToBech32, secp256k1::XOnlyPublicKey};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::nostr::DirectMessage;
+
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct MessageProp {
+    pub outgoing: bool,
+    /// `None` until decrypted
+    pub content: Option<AttrValue>,
+    pub created_at: u64,
+}
+
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct ConversationProp {
+    /// Hex pubkey of the other party
+    pub pubkey: AttrValue,
+    /// Bech32 pubkey of the other party
+    pub npub: AttrValue,
+    pub messages: Vec<MessageProp>,
+}
+
+impl ConversationProp {
+    /// Group direct messages into conversations, most recently active first
+    pub fn from_messages(messages: Vec<DirectMessage>) -> Vec<Self> {
+        let mut conversations: HashMap<XOnlyPublicKey, Vec<MessageProp>> = HashMap::new();
+
+        for message in messages {
+            conversations
+                .entry(message.counterparty)
+                .or_default()
+                .push(MessageProp {
+                    outgoing: message.outgoing,
+                    content: message.content.map(AttrValue::from),
+                    created_at: message.created_at,
+                });
+        }
+
+        let mut conversations = conversations
+            .into_iter()
+            .map(|(pubkey, mut messages)| {
+                messages.sort_by_key(|m| m.created_at);
+                let npub = match pubkey.to_bech32() {
+                    Ok(key) => key.into(),
+                    Err(_) => pubkey.to_string().into(),
+                };
+                Self {
+                    pubkey: pubkey.to_string().into(),
+                    npub,
+                    messages,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        conversations.sort_by_key(|c| {
+            std::cmp::Reverse(c.messages.last().map(|m| m.created_at).unwrap_or_default())
+        });
+
+        conversations
+    }
+}
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    pub conversations: Vec<ConversationProp>,
+    pub loading: bool,
+    pub refresh_cb: Callback<MouseEvent>,
+    /// Hex pubkey of the conversation to decrypt
+    pub decrypt_cb: Callback<AttrValue>,
+    /// Receiver pubkey and message text
+    pub send_cb: Callback<(AttrValue, AttrValue)>,
+}
+
+pub enum Msg {
+    Select(AttrValue),
+    Reply,
+    NewConversation,
+}
+
+pub struct Messages {
+    selected: Option<AttrValue>,
+    reply_text: NodeRef,
+    new_recipient: NodeRef,
+    new_text: NodeRef,
+}
+
+impl Component for Messages {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            selected: None,
+            reply_text: NodeRef::default(),
+            new_recipient: NodeRef::default(),
+            new_text: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Select(pubkey) => {
+                ctx.props().decrypt_cb.emit(pubkey.clone());
+                self.selected = Some(pubkey);
+            }
+            Msg::Reply => {
+                if let (Some(pubkey), Some(input)) = (
+                    self.selected.clone(),
+                    self.reply_text.cast::<HtmlInputElement>(),
+                ) {
+                    ctx.props().send_cb.emit((pubkey, input.value().into()));
+                    input.set_value("");
+                }
+            }
+            Msg::NewConversation => {
+                if let (Some(recipient), Some(text)) = (
+                    self.new_recipient.cast::<HtmlInputElement>(),
+                    self.new_text.cast::<HtmlInputElement>(),
+                ) {
+                    ctx.props()
+                        .send_cb
+                        .emit((recipient.value().into(), text.value().into()));
+                    text.set_value("");
+                }
+            }
+        }
+
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let selected = self.selected.as_ref().and_then(|pubkey| {
+            ctx.props()
+                .conversations
+                .iter()
+                .find(|c| &c.pubkey == pubkey)
+        });
+
+        html! {
+            <>
+            <h2 class="text-4xl font-extrabold dark:text-white">{ "Messages" }</h2>
+            <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.props().refresh_cb.clone()}>{ "Refresh" }</button>
+            if ctx.props().loading {
+                <p class="text-base text-gray-500 dark:text-gray-400">{ "Loading messages..." }</p>
+            }
+
+            // List of conversations
+            <ul class="max-w-md divide-y divide-gray-200 dark:divide-gray-700">
+                {
+                    ctx.props().conversations.iter().map(|conversation| {
+                        let pubkey = conversation.pubkey.clone();
+                        let onclick = ctx.link().callback(move |_| Msg::Select(pubkey.clone()));
+                        let last = match conversation.messages.last() {
+                            Some(MessageProp { content: Some(content), .. }) => content.clone(),
+                            Some(_) => "Encrypted message".into(),
+                            None => AttrValue::default(),
+                        };
+                        html! {
+                            <li class="py-3 cursor-pointer" {onclick}>
+                                <p class="text-sm font-medium text-gray-900 truncate dark:text-white">{ conversation.npub.clone() }</p>
+                                <p class="text-sm text-gray-500 truncate dark:text-gray-400">{ last }</p>
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+
+            // Selected conversation
+            if let Some(conversation) = selected {
+                <div class="p-4">
+                    <p class="text-2xl text-gray-900 dark:text-white">{ conversation.npub.clone() }</p>
+                    if conversation.messages.iter().any(|message| message.content.is_none()) {
+                        <button type="button" class="text-sm font-medium text-purple-700 hover:underline dark:text-purple-400" onclick={
+                            let pubkey = conversation.pubkey.clone();
+                            ctx.link().callback(move |_| Msg::Select(pubkey.clone()))
+                        }>{ "Decrypt earlier messages" }</button>
+                    }
+                    {
+                        conversation.messages.iter().filter(|message| message.content.is_some()).map(|message| {
+                            let class = if message.outgoing {
+                                "p-2 mb-2 ml-auto max-w-md rounded-lg bg-purple-700 text-white"
+                            } else {
+                                "p-2 mb-2 mr-auto max-w-md rounded-lg bg-gray-100 text-gray-900 dark:bg-gray-700 dark:text-white"
+                            };
+                            html! { <div {class}>{ message.content.clone().unwrap_or_default() }</div> }
+                        }).collect::<Html>()
+                    }
+                    <input type="text" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="Reply..." ref={self.reply_text.clone()}/>
+                    <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::Reply)}>{ "Send" }</button>
+                </div>
+            }
+
+            // Start a new conversation
+            <div class="mb-6">
+                <label for="new-recipient" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "New message to (npub or hex)" }</label>
+                <input type="text" id="new-recipient" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.new_recipient.clone()}/>
+                <input type="text" class="mt-2 bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="Message..." ref={self.new_text.clone()}/>
+                <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::NewConversation)}>{ "Send" }</button>
+            </div>
+            </>
+        }
+    }
+}
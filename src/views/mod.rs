@@ -1,3 +1,8 @@
 pub mod connect;
+pub mod edit_profile;
+pub mod history;
 pub mod home;
+pub mod messages;
 pub mod settings;
+pub mod thread;
+pub mod unlock;
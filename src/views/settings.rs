@@ -1,22 +1,35 @@
 use std::collections::HashSet;
 
 use log::debug;
-use nostr_sdk::{nips::nip19::ToBech32, secp256k1::XOnlyPublicKey, url::Url};
-use web_sys::HtmlInputElement;
+use nostr_sdk::{
+    nips::{nip19::ToBech32, nip26::Conditions},
+    secp256k1::{schnorr::Signature, XOnlyPublicKey},
+    url::Url,
+};
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
 use yew::prelude::*;
 
 use crate::components::delegate::Delegate;
+use crate::components::profile::{Profile, ProfileProp};
+use crate::services::nostr::DelegationInfo;
+use crate::services::store::StorageBackend;
+use crate::views::unlock::check_new_passphrase;
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct DelegationInfoProp {
+    /// Hex delegation signature, identifies the delegation
+    pub signature: AttrValue,
     pub delegator_pubkey: AttrValue,
     pub valid_from: AttrValue,
     pub valid_to: AttrValue,
     pub kinds: AttrValue,
+    /// Metadata of the delegator
+    pub profile: ProfileProp,
 }
 
 impl DelegationInfoProp {
     pub fn new(
+        signature: Signature,
         pubkey: XOnlyPublicKey,
         from: Option<u64>,
         to: Option<u64>,
@@ -45,37 +58,99 @@ impl DelegationInfoProp {
             .into();
 
         Self {
+            signature: signature.to_string().into(),
             delegator_pubkey,
             valid_from,
             valid_to,
             kinds,
+            profile: ProfileProp::new(pubkey, None, false),
         }
     }
 }
 
+impl DelegationInfoProp {
+    pub fn with_profile(mut self, profile: ProfileProp) -> Self {
+        self.profile = profile;
+        self
+    }
+}
+
+impl From<&DelegationInfo> for DelegationInfoProp {
+    fn from(info: &DelegationInfo) -> Self {
+        Self::new(
+            info.signature,
+            info.delegator_pubkey,
+            info.created_after(),
+            info.created_before(),
+            info.kinds(),
+        )
+    }
+}
+
 #[derive(Properties, PartialEq, Default, Clone)]
 pub struct Props {
     pub app_pubkey: AttrValue,
-    pub delegation_info: Option<DelegationInfoProp>,
+    /// Metadata of the remote signer
+    pub remote_signer: Option<ProfileProp>,
+    pub delegations: Vec<DelegationInfoProp>,
     pub connect_relay: AttrValue,
     pub relays: HashSet<Url>,
     pub update_connect_relay_cb: Callback<AttrValue>,
     pub add_relay_cb: Callback<AttrValue>,
     pub logout_cb: Callback<MouseEvent>,
     pub remove_relay_cb: Callback<Url>,
-    pub delegation_cb: Callback<(u64, Vec<u64>)>,
+    pub delegation_cb: Callback<Conditions>,
+    pub remove_delegation_cb: Callback<AttrValue>,
+    pub auto_renew: bool,
+    pub auto_renew_cb: Callback<bool>,
+    pub import_delegation_cb: Callback<DelegationToken>,
+    pub exported_bundle: Option<AttrValue>,
+    /// Password
+    pub export_bundle_cb: Callback<AttrValue>,
+    /// Bundle and password
+    pub import_bundle_cb: Callback<(AttrValue, AttrValue)>,
+    pub storage_backend: StorageBackend,
+    pub storage_backend_cb: Callback<StorageBackend>,
+}
+
+/// Delegation token produced by another tool
+#[derive(Debug, PartialEq, Clone)]
+pub enum DelegationToken {
+    /// JSON tag `["delegation", <pubkey>, <conditions>, <sig>]`
+    Tag(AttrValue),
+    /// Delegator pubkey, conditions query string and signature
+    Parts {
+        delegator: AttrValue,
+        conditions: AttrValue,
+        signature: AttrValue,
+    },
 }
 
 pub enum Msg {
     UpdateConnectRelay,
     AddRelay,
     DeleteRelay(Url),
-    Delegate((u64, Vec<u64>)),
+    Delegate(Conditions),
+    RemoveDelegation(AttrValue),
+    ImportDelegationTag,
+    ImportDelegationParts,
+    ExportBundle,
+    ImportBundle,
 }
 
 pub struct Settings {
     connect_relay: NodeRef,
     new_relay: NodeRef,
+    delegation_tag: NodeRef,
+    delegator_pubkey: NodeRef,
+    delegation_conditions: NodeRef,
+    delegation_signature: NodeRef,
+    export_password: NodeRef,
+    export_confirm: NodeRef,
+    /// Export password was too short or not confirmed
+    export_error: Option<&'static str>,
+    import_bundle: NodeRef,
+    import_password: NodeRef,
 }
 
 impl Component for Settings {
@@ -86,6 +161,15 @@ impl Component for Settings {
         Self {
             connect_relay: NodeRef::default(),
             new_relay: NodeRef::default(),
+            delegation_tag: NodeRef::default(),
+            delegator_pubkey: NodeRef::default(),
+            delegation_conditions: NodeRef::default(),
+            delegation_signature: NodeRef::default(),
+            export_password: NodeRef::default(),
+            export_confirm: NodeRef::default(),
+            export_error: None,
+            import_bundle: NodeRef::default(),
+            import_password: NodeRef::default(),
         }
     }
 
@@ -108,9 +192,61 @@ impl Component for Settings {
             Msg::DeleteRelay(relay) => {
                 ctx.props().remove_relay_cb.emit(relay);
             }
-            Msg::Delegate((expiration, kinds)) => {
-                debug!("Delegate: {}", expiration);
-                ctx.props().delegation_cb.emit((expiration, kinds));
+            Msg::Delegate(conditions) => {
+                debug!("Delegate: {}", conditions);
+                ctx.props().delegation_cb.emit(conditions);
+            }
+            Msg::RemoveDelegation(signature) => {
+                ctx.props().remove_delegation_cb.emit(signature);
+            }
+            Msg::ImportDelegationTag => {
+                if let Some(tag) = self.delegation_tag.cast::<HtmlInputElement>() {
+                    ctx.props()
+                        .import_delegation_cb
+                        .emit(DelegationToken::Tag(tag.value().into()));
+                    tag.set_value("");
+                }
+            }
+            Msg::ImportDelegationParts => {
+                if let (Some(delegator), Some(conditions), Some(signature)) = (
+                    self.delegator_pubkey.cast::<HtmlInputElement>(),
+                    self.delegation_conditions.cast::<HtmlInputElement>(),
+                    self.delegation_signature.cast::<HtmlInputElement>(),
+                ) {
+                    ctx.props()
+                        .import_delegation_cb
+                        .emit(DelegationToken::Parts {
+                            delegator: delegator.value().into(),
+                            conditions: conditions.value().into(),
+                            signature: signature.value().into(),
+                        });
+                }
+            }
+            Msg::ExportBundle => {
+                if let (Some(password), Some(confirm)) = (
+                    self.export_password.cast::<HtmlInputElement>(),
+                    self.export_confirm.cast::<HtmlInputElement>(),
+                ) {
+                    // The password becomes the passphrase of the account it is imported as
+                    self.export_error =
+                        check_new_passphrase(&password.value(), &confirm.value()).err();
+                    if self.export_error.is_none() {
+                        ctx.props().export_bundle_cb.emit(password.value().into());
+                    }
+                    password.set_value("");
+                    confirm.set_value("");
+                }
+            }
+            Msg::ImportBundle => {
+                if let (Some(bundle), Some(password)) = (
+                    self.import_bundle.cast::<HtmlTextAreaElement>(),
+                    self.import_password.cast::<HtmlInputElement>(),
+                ) {
+                    ctx.props()
+                        .import_bundle_cb
+                        .emit((bundle.value().into(), password.value().into()));
+                    password.set_value("");
+                }
             }
         }
 
@@ -122,31 +258,110 @@ impl Component for Settings {
         let add_relay = ctx.link().callback(|_| Msg::AddRelay);
 
         let delegate_cb = ctx.link().callback(Msg::Delegate);
+        let auto_renew_cb = ctx.props().auto_renew_cb.clone();
+        let auto_renew = Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            auto_renew_cb.emit(input.checked());
+        });
+        let storage_backend_cb = ctx.props().storage_backend_cb.clone();
+        let storage_backend = Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            match select.value().parse() {
+                Ok(backend) => storage_backend_cb.emit(backend),
+                Err(err) => debug!("{}", err),
+            }
+        });
         html! {
             <>
             <h2 class="text-4xl font-extrabold dark:text-white">{ "Settings" }</h2>
-            // Display name and key of delgator
-            if let Some(delegator_info) = ctx.props().clone().delegation_info {
-                <div class="flex items-center space-x-4 p-3">
-                    <img class="w-10 h-10 rounded-full" src="/docs/images/people/profile-picture-5.jpg" alt=""/ >
-                    <div class="font-medium dark:text-white">
-                        // TODO: Pull this from profile
-                        <div>{ "John Doe" }</div>
-                        <div class="text-sm text-gray-500 dark:text-gray-400"> {delegator_info.delegator_pubkey } </div>
-                    </div>
-                </div>
+            if let Some(remote_signer) = ctx.props().remote_signer.clone() {
+                <p class="text-2xl text-gray-900 dark:text-white">{ "Remote signer" }</p>
+                <Profile profile={remote_signer} />
+            }
 
-                <div class="p-4">
-                    <p class="text-2xl text-gray-900 dark:text-white">{ "Delegation" }</p>
-                    <p class="text-base text-gray-900 dark:text-white">{ format!("Valid: {} - {}", delegator_info.valid_from, delegator_info.valid_to) } </p>
+            // Display name and key of delgators
+            {
+                ctx.props().delegations.iter().map(|delegator_info| {
+                    let signature = delegator_info.signature.clone();
+                    let remove = ctx.link().callback(move |_| Msg::RemoveDelegation(signature.clone()));
+                    html! {
+                    <>
+                    <Profile profile={delegator_info.profile.clone()} />
 
-                </div>
+                    <div class="p-4">
+                        <p class="text-2xl text-gray-900 dark:text-white">{ "Delegation" }</p>
+                        <p class="text-base text-gray-900 dark:text-white">{ format!("Valid: {} - {}", delegator_info.valid_from, delegator_info.valid_to) } </p>
+                        <p class="text-base text-gray-900 dark:text-white">{ format!("Kinds: {}", delegator_info.kinds) } </p>
+                        <button type="button" class="text-purple-700 border border-purple-700 hover:bg-purple-700 hover:text-white focus:ring-4 focus:outline-none focus:ring-purple-300 font-medium rounded-lg text-sm px-3 py-1 mt-2 dark:border-purple-500 dark:text-purple-500 dark:hover:text-white dark:focus:ring-purple-800 dark:hover:bg-purple-500" onclick={remove}>{ "Remove" }</button>
+                    </div>
+                    </>
+                    }
+                }).collect::<Html>()
             }
 
             <div>
                 <Delegate {delegate_cb}/>
             </div>
 
+            // Renew delegations before they expire
+            <div class="flex items-center mb-6">
+                <input type="checkbox" id="auto-renew" class="w-4 h-4 text-purple-600 bg-gray-100 border-gray-300 rounded focus:ring-purple-500 dark:focus:ring-purple-600 dark:ring-offset-gray-800 focus:ring-2 dark:bg-gray-700 dark:border-gray-600" checked={ctx.props().auto_renew} onchange={auto_renew}/>
+                <label for="auto-renew" class="ml-2 text-sm font-medium text-gray-900 dark:text-gray-300">{ "Automatically renew expiring delegations" }</label>
+            </div>
+
+            // Import a delegation token created by another tool
+            <div class="mb-6">
+                <h2 class="mb-2 text-lg font-semibold text-gray-900 dark:text-white">{ "Import delegation" }</h2>
+                <label for="delegation-tag" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Delegation tag" }</label>
+                <input type="text" id="delegation-tag" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="[\"delegation\", \"<pubkey>\", \"<conditions>\", \"<sig>\"]" ref={self.delegation_tag.clone()}/>
+                <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::ImportDelegationTag)}>{ "Import tag" }</button>
+
+                <label for="delegator-pubkey" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Delegator pubkey or NIP-05" }</label>
+                <input type="text" id="delegator-pubkey" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.delegator_pubkey.clone()}/>
+                <label for="delegation-conditions" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Conditions" }</label>
+                <input type="text" id="delegation-conditions" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" placeholder="kind=1&created_at>1680000000" ref={self.delegation_conditions.clone()}/>
+                <label for="delegation-signature" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Signature" }</label>
+                <input type="text" id="delegation-signature" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.delegation_signature.clone()}/>
+                <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::ImportDelegationParts)}>{ "Import" }</button>
+            </div>
+
+            // Export and import the session as a password encrypted bundle
+            <div class="mb-6">
+                <h2 class="mb-2 text-lg font-semibold text-gray-900 dark:text-white">{ "Session bundle" }</h2>
+                <label for="export-password" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Password" }</label>
+                <input type="password" id="export-password" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.export_password.clone()}/>
+                <label for="export-confirm" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Confirm password" }</label>
+                <input type="password" id="export-confirm" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.export_confirm.clone()}/>
+                if let Some(error) = self.export_error {
+                    <p class="text-sm text-red-600 dark:text-red-400">{ error }</p>
+                }
+                <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::ExportBundle)}>{ "Export" }</button>
+                if let Some(bundle) = ctx.props().exported_bundle.clone() {
+                    <textarea rows="4" readonly=true class="block p-2.5 mb-2 w-full text-sm text-gray-900 bg-gray-50 rounded-lg border border-gray-300 dark:bg-gray-700 dark:border-gray-600 dark:text-white" value={bundle}></textarea>
+                }
+
+                <label for="import-bundle" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Import bundle as a new account" }</label>
+                <textarea id="import-bundle" rows="4" class="block p-2.5 mb-2 w-full text-sm text-gray-900 bg-gray-50 rounded-lg border border-gray-300 focus:ring-blue-500 focus:border-blue-500 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.import_bundle.clone()}></textarea>
+                <label for="import-password" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Password" }</label>
+                <input type="password" id="import-password" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.import_password.clone()}/>
+                <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" onclick={ctx.link().callback(|_| Msg::ImportBundle)}>{ "Import" }</button>
+            </div>
+
+            // Where the session state is kept
+            <div class="mb-6">
+                <label for="storage-backend" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Session storage" }</label>
+                <select id="storage-backend" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" onchange={storage_backend}>
+                    {
+                        StorageBackend::ALL.iter().map(|backend| html! {
+                            <option value={backend.as_str()} selected={*backend == ctx.props().storage_backend}>{ backend.to_string() }</option>
+                        }).collect::<Html>()
+                    }
+                </select>
+                <p class="mt-2 text-sm text-gray-500 dark:text-gray-400">
+                    { "Applies to the app key, signer, delegations, outbox and cached profiles. Relays, accounts and preferences are always kept in local storage" }
+                </p>
+            </div>
+
             // Text box of connect relay that is editable
             <div class="mb-6">
                 <label for="default-input" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{"Connect Relay"}</label>
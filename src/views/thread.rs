@@ -0,0 +1,64 @@
+use yew::prelude::*;
+
+use crate::components::note::{Note, NoteProp};
+use crate::services::interactions::Reaction;
+
+/// Deepest indentation of replies, deeper replies are shown at this level
+const MAX_INDENT: usize = 6;
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    /// Notes depth first with their depth in the reply tree
+    pub notes: Vec<(usize, NoteProp)>,
+    pub loading: bool,
+    pub back_cb: Callback<MouseEvent>,
+    /// Note id and reply text
+    pub reply_cb: Callback<(AttrValue, AttrValue)>,
+    /// Note id and quoting text
+    pub quote_cb: Callback<(AttrValue, AttrValue)>,
+    /// Note id
+    pub thread_cb: Callback<AttrValue>,
+    /// Note id and reaction
+    pub react_cb: Callback<(AttrValue, Reaction)>,
+    /// Note id
+    pub repost_cb: Callback<AttrValue>,
+}
+
+pub struct Thread;
+
+impl Component for Thread {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+
+        html! {
+            <div class="p-4 mb-20">
+            <button type="button" class="text-purple-700 border border-purple-700 hover:bg-purple-700 hover:text-white focus:ring-4 focus:outline-none focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 text-center dark:border-purple-500 dark:text-purple-500 dark:hover:text-white dark:focus:ring-purple-800 dark:hover:bg-purple-500" onclick={props.back_cb.clone()}>{ "Back" }</button>
+            <h2 class="text-4xl font-extrabold dark:text-white">{ "Thread" }</h2>
+            if props.loading {
+                <p class="text-base text-gray-500 dark:text-gray-400">{ "Loading thread..." }</p>
+            } else if props.notes.is_empty() {
+                <p class="text-base text-gray-500 dark:text-gray-400">{ "Note not found" }</p>
+            }
+            <ul class="max-w-md divide-y divide-gray-200 dark:divide-gray-700">
+                {
+                    props.notes.iter().map(|(depth, note)| {
+                        let style = format!("margin-left: {}rem", (*depth).min(MAX_INDENT));
+                        html! {
+                            <li key={note.id.as_str()} {style} class="border-l border-purple-200 pl-2 dark:border-purple-800">
+                                <Note note={note.clone()} reply_cb={props.reply_cb.clone()} quote_cb={props.quote_cb.clone()} thread_cb={props.thread_cb.clone()} react_cb={props.react_cb.clone()} repost_cb={props.repost_cb.clone()} />
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use log::debug;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// Shortest passphrase accepted for a new app key
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Check a new passphrase is long enough and was typed the same twice
+pub fn check_new_passphrase(passphrase: &str, confirm: &str) -> Result<(), &'static str> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err("Passphrase must be at least 8 characters");
+    }
+    if passphrase != confirm {
+        return Err("Passphrases do not match");
+    }
+    Ok(())
+}
+
+#[derive(Properties, PartialEq, Default, Clone)]
+pub struct Props {
+    /// No key is saved yet, ask for a new passphrase
+    pub create: bool,
+    pub unlock_cb: Callback<AttrValue>,
+    /// Delete the account and its saved key, for a forgotten passphrase
+    pub forget_cb: Callback<MouseEvent>,
+}
+
+pub enum Msg {
+    Unlock,
+    /// Ask before forgetting the account
+    Forget(bool),
+}
+
+pub struct Unlock {
+    passphrase: NodeRef,
+    confirm: NodeRef,
+    error: Option<&'static str>,
+    /// Waiting for the user to confirm forgetting the account
+    forget: bool,
+}
+
+impl Component for Unlock {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            passphrase: NodeRef::default(),
+            confirm: NodeRef::default(),
+            error: None,
+            forget: false,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Unlock => {
+                debug!("Unlock");
+                let passphrase = match self.passphrase.cast::<HtmlInputElement>() {
+                    Some(input) => input.value(),
+                    None => return false,
+                };
+
+                if ctx.props().create {
+                    let confirm = self
+                        .confirm
+                        .cast::<HtmlInputElement>()
+                        .map(|input| input.value())
+                        .unwrap_or_default();
+                    if let Err(error) = check_new_passphrase(&passphrase, &confirm) {
+                        self.error = Some(error);
+                        return true;
+                    }
+                }
+
+                self.error = None;
+                ctx.props().unlock_cb.emit(passphrase.into());
+                true
+            }
+            Msg::Forget(forget) => {
+                self.forget = forget;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::Unlock);
+        let create = ctx.props().create;
+
+        html! {
+            <div class="p-4 mb-6">
+                <h2 class="mb-2 text-lg font-semibold text-gray-900 dark:text-white">
+                    { if create { "Protect app key" } else { "Unlock" } }
+                </h2>
+                <p class="mb-2 text-sm text-gray-500 dark:text-gray-400">
+                    { if create {
+                        "The app key is saved encrypted with this passphrase"
+                    } else {
+                        "Enter the passphrase of the saved app key"
+                    } }
+                </p>
+                <label for="passphrase" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Passphrase" }</label>
+                <input type="password" id="passphrase" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 mb-2 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.passphrase.clone()}/>
+                if create {
+                    <label for="confirm-passphrase" class="block mb-2 text-sm font-medium text-gray-900 dark:text-white">{ "Confirm passphrase" }</label>
+                    <input type="password" id="confirm-passphrase" class="bg-gray-50 border border-gray-300 text-gray-900 text-sm rounded-lg focus:ring-blue-500 focus:border-blue-500 block w-full p-2.5 mb-2 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500" ref={self.confirm.clone()}/>
+                }
+                if let Some(error) = self.error {
+                    <p class="text-sm text-red-600 dark:text-red-400">{ error }</p>
+                }
+                <button type="button" class="focus:outline-none text-white bg-purple-700 hover:bg-purple-800 focus:ring-4 focus:ring-purple-300 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-purple-600 dark:hover:bg-purple-700 dark:focus:ring-purple-900" {onclick}>
+                    { if create { "Save" } else { "Unlock" } }
+                </button>
+                if !create {
+                    if self.forget {
+                        <p class="mb-2 text-sm text-red-600 dark:text-red-400">
+                            { "The saved app key and session of this account are deleted, delegations held by it can not be used again" }
+                        </p>
+                        <button type="button" class="focus:outline-none text-white bg-red-700 hover:bg-red-800 focus:ring-4 focus:ring-red-300 font-medium rounded-lg text-sm px-5 py-2.5 mr-2 mb-2 dark:bg-red-600 dark:hover:bg-red-700 dark:focus:ring-red-900" onclick={ctx.props().forget_cb.clone()}>
+                            { "Forget account" }
+                        </button>
+                        <button type="button" class="text-gray-900 bg-white border border-gray-300 focus:outline-none hover:bg-gray-100 focus:ring-4 focus:ring-gray-200 font-medium rounded-lg text-sm px-5 py-2.5 mb-2 dark:bg-gray-800 dark:text-white dark:border-gray-600 dark:hover:bg-gray-700 dark:hover:border-gray-600 dark:focus:ring-gray-700" onclick={ctx.link().callback(|_| Msg::Forget(false))}>
+                            { "Cancel" }
+                        </button>
+                    } else {
+                        <button type="button" class="block text-sm text-gray-500 hover:underline dark:text-gray-400" onclick={ctx.link().callback(|_| Msg::Forget(true))}>
+                            { "Forgot passphrase? Forget this account" }
+                        </button>
+                    }
+                }
+            </div>
+        }
+    }
+}